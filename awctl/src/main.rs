@@ -0,0 +1,586 @@
+mod proxy;
+
+use chrono::{Days, Local, NaiveDate, Utc, Weekday};
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+use serde_json::json;
+use shared::dbus::{DBus, Host, Interface};
+use shared::types::daemon::TimerAction;
+use shared::types::day;
+use shared::types::schema::Timer;
+use std::error::Error;
+use std::io::{self, BufRead};
+use std::process::ExitCode;
+use zbus::Connection;
+
+use crate::proxy::DaemonContextProxy;
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+/// The largest limit of a timer in minutes, which spans a whole day.
+const MAX_LIMIT_MINUTES: i64 = 24 * 60;
+
+#[derive(Parser)]
+#[command(name = "awctl", about = "Control the Activity Warden user daemon.")]
+struct Cli {
+    /// Print machine-readable JSON instead of text.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Manage the timers enforced by the daemon.
+    #[command(subcommand)]
+    Timers(TimersCommand),
+    /// Show the current focus and the time remaining on it.
+    Status,
+    /// Show the time spent per display name.
+    Stats {
+        /// The day (YYYY-MM-DD) from which to count, starting at the configured hour. Defaults to today.
+        #[arg(long)]
+        since: Option<NaiveDate>,
+    },
+    /// Show the periods in which activity went untracked and the state of the watchers.
+    Coverage {
+        /// The day (YYYY-MM-DD) to report on, starting at the configured hour. Defaults to today.
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Lock the daemon. A password must be configured first.
+    Lock,
+    /// Unlock the daemon with the current password or a partner code.
+    Unlock {
+        /// Why the daemon is being unlocked. Required in partner mode.
+        #[arg(long, default_value = "")]
+        reason: String,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Manage the password that guards the lock.
+    #[command(subcommand)]
+    Password(PasswordCommand),
+    /// Let an accountability partner unlock the daemon with one-time codes.
+    #[command(subcommand)]
+    Partner(PartnerCommand),
+    /// Inspect or cancel the changes waiting out the cooling-off period.
+    #[command(subcommand)]
+    Pending(PendingCommand),
+    /// List the unlocks of the daemon along with their reasons.
+    Unlocks {
+        /// The day (YYYY-MM-DD) from which to list, starting at the configured hour. Defaults to 7 days ago.
+        #[arg(long)]
+        since: Option<NaiveDate>,
+    },
+}
+
+#[derive(Subcommand)]
+enum PendingCommand {
+    /// List the pending unlock and timer changes.
+    List,
+    /// Cancel a pending timer change by its id.
+    Cancel { id: i64 },
+    /// Cancel the pending unlock.
+    CancelUnlock,
+}
+
+#[derive(Subcommand)]
+enum PartnerCommand {
+    /// Generate a new secret for the partner, replacing any previous one.
+    Enable(PasswordArgs),
+    /// Stop accepting partner codes.
+    Disable(PasswordArgs),
+}
+
+#[derive(Subcommand)]
+enum PasswordCommand {
+    /// Set a new password, replacing the current one.
+    Set,
+    /// Remove the password, which also unlocks the daemon.
+    Clear(PasswordArgs),
+}
+
+#[derive(Subcommand)]
+enum TimersCommand {
+    /// List every timer.
+    List,
+    /// Create a new timer.
+    Add(TimerArgs),
+    /// Remove an existing timer.
+    Rm {
+        display_name: String,
+        #[arg(long, default_value_t = Host::FirefoxWatcher)]
+        host: Host,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+    /// Change the limit or the days of an existing timer.
+    Edit {
+        display_name: String,
+        #[arg(long, default_value_t = Host::FirefoxWatcher)]
+        host: Host,
+        /// The minutes allowed per day.
+        #[arg(long, value_parser = clap::value_parser!(u32).range(0..=MAX_LIMIT_MINUTES))]
+        limit: Option<u32>,
+        /// The days on which the limit applies, i.e. `mon,tue`.
+        #[arg(long, value_delimiter = ',')]
+        days: Option<Vec<Weekday>>,
+        #[command(flatten)]
+        password: PasswordArgs,
+    },
+}
+
+#[derive(Args)]
+struct TimerArgs {
+    display_name: String,
+    #[arg(long, default_value_t = Host::FirefoxWatcher)]
+    host: Host,
+    /// The minutes allowed per day.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=MAX_LIMIT_MINUTES))]
+    limit: u32,
+    /// The days on which the limit applies, i.e. `mon,tue`. The display
+    /// name is blocked entirely on other days. Defaults to every day.
+    #[arg(long, value_delimiter = ',')]
+    days: Option<Vec<Weekday>>,
+}
+
+#[derive(Args)]
+struct PasswordArgs {
+    /// Read the password from the first line of standard input.
+    #[arg(long)]
+    password_stdin: bool,
+}
+
+#[derive(Serialize)]
+struct Usage {
+    host: Host,
+    display_name: String,
+    duration: u32,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> CliResult {
+    let conn = Connection::session().await?;
+    let proxy = DaemonContextProxy::builder(&conn)
+        .destination(DBus::host_name(&Host::UserDaemon))?
+        .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext))?
+        .build()
+        .await?;
+
+    match cli.command {
+        Command::Timers(cmd) => run_timers(&proxy, cmd, cli.json).await,
+        Command::Status => run_status(&proxy, cli.json).await,
+        Command::Stats { since } => run_stats(&proxy, since, cli.json).await,
+        Command::Coverage { date } => run_coverage(&proxy, date, cli.json).await,
+        Command::Lock => run_lock(&proxy, cli.json).await,
+        Command::Unlock { reason, password } => run_unlock(&proxy, reason, password, cli.json).await,
+        Command::Password(cmd) => run_password(&proxy, cmd).await,
+        Command::Partner(cmd) => run_partner(&proxy, cmd, cli.json).await,
+        Command::Pending(cmd) => run_pending(&proxy, cmd, cli.json).await,
+        Command::Unlocks { since } => run_unlocks(&proxy, since, cli.json).await,
+    }
+}
+
+async fn run_timers(proxy: &DaemonContextProxy<'_>, cmd: TimersCommand, as_json: bool) -> CliResult {
+    match cmd {
+        TimersCommand::List => {
+            let timers = proxy.get_daemon_snapshot().await?.timers;
+            if as_json {
+                println!("{}", serde_json::to_string_pretty(&timers)?);
+            } else {
+                for t in &timers {
+                    println!(
+                        "{:<32} {:<18} {:>8} {}",
+                        t.display_name,
+                        t.host,
+                        fmt_duration(t.time_limit),
+                        fmt_days(&t.allowed_days),
+                    );
+                }
+            }
+        },
+        TimersCommand::Add(args) => {
+            if find_timer(proxy, &args.display_name, &args.host).await.is_ok() {
+                return Err(format!("A timer for '{}' on {} already exists, use `timers edit` instead", args.display_name, args.host).into());
+            }
+            let timer = Timer {
+                display_name: args.display_name,
+                host: args.host,
+                time_limit: args.limit * 60,
+                allowed_days: days_to_mask(args.days.as_deref()),
+            };
+            proxy.insert_timer(timer.clone()).await?;
+            print_timer("Added", &timer, 0, as_json)?;
+        },
+        TimersCommand::Rm { display_name, host, password } => {
+            let timer = find_timer(proxy, &display_name, &host).await?;
+            ensure_can_edit(proxy, &password).await?;
+            let delay = proxy.delete_timer(timer.clone()).await?;
+            print_timer("Removed", &timer, delay, as_json)?;
+        },
+        TimersCommand::Edit { display_name, host, limit, days, password } => {
+            let mut timer = find_timer(proxy, &display_name, &host).await?;
+            ensure_can_edit(proxy, &password).await?;
+            if let Some(limit) = limit {
+                timer.time_limit = limit * 60;
+            }
+            if let Some(days) = days {
+                timer.allowed_days = days_to_mask(Some(&days));
+            }
+            let delay = proxy.update_timer(timer.clone()).await?;
+            print_timer("Updated", &timer, delay, as_json)?;
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_status(proxy: &DaemonContextProxy<'_>, as_json: bool) -> CliResult {
+    let sessions = proxy.get_status().await?;
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&sessions)?);
+        return Ok(());
+    }
+
+    if sessions.is_empty() {
+        println!("Nothing is focused.");
+    }
+    for s in &sessions {
+        let mut remaining = if s.has_timer {
+            format!("{} remaining", fmt_duration(s.remaining))
+        } else {
+            "no timer".to_string()
+        };
+        if !s.counting {
+            remaining.push_str(", paused");
+        }
+        println!(
+            "{}: {} since {} ({} today, {})",
+            s.host,
+            s.display_name,
+            s.started_at.with_timezone(&Local).format("%H:%M:%S"),
+            fmt_duration(s.duration),
+            remaining,
+        );
+    }
+
+    Ok(())
+}
+
+async fn run_stats(proxy: &DaemonContextProxy<'_>, since: Option<NaiveDate>, as_json: bool) -> CliResult {
+    let day_start_hour = proxy.day_start_hour().await?;
+    let since = since.unwrap_or_else(|| day::tracking_day(Utc::now(), day_start_hour));
+    let durations = proxy.get_durations(day::day_start(since, day_start_hour), Utc::now()).await?;
+
+    // List the time per display name, largest first.
+    let mut usage = durations.into_iter()
+        .flat_map(|(host, dur_map)| {
+            dur_map.into_iter().map(move |(display_name, duration)| Usage {
+                host: host.clone(),
+                display_name,
+                duration,
+            })
+        })
+        .collect::<Vec<Usage>>();
+    usage.sort_by(|a, b| b.duration.cmp(&a.duration).then_with(|| a.display_name.cmp(&b.display_name)));
+
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&usage)?);
+    } else {
+        for u in &usage {
+            println!("{:<32} {:<18} {:>8}", u.display_name, u.host, fmt_duration(u.duration));
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_coverage(proxy: &DaemonContextProxy<'_>, date: Option<NaiveDate>, as_json: bool) -> CliResult {
+    let day_start_hour = proxy.day_start_hour().await?;
+    let date = date.unwrap_or_else(|| day::tracking_day(Utc::now(), day_start_hour));
+    let start = day::day_start(date, day_start_hour);
+    let end = day::day_start(date + Days::new(1), day_start_hour);
+    let coverage = proxy.get_coverage(start, end).await?;
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&coverage)?);
+        return Ok(());
+    }
+
+    let observed = (end.min(Utc::now()) - start).num_seconds().max(0) as u32;
+    println!(
+        "Covered {} of {} on {}.",
+        fmt_duration(observed.saturating_sub(coverage.uncovered)),
+        fmt_duration(observed),
+        date,
+    );
+    for w in &coverage.watchers {
+        let state = if w.connected { "connected" } else { "disconnected" };
+        println!(
+            "{:<18} {:<12} last seen {}",
+            w.host,
+            state,
+            w.last_seen.with_timezone(&Local).format("%H:%M:%S"),
+        );
+    }
+    for g in &coverage.gaps {
+        let end = if g.ongoing {
+            "now".to_string()
+        } else {
+            g.end.with_timezone(&Local).format("%m-%d %H:%M:%S").to_string()
+        };
+        println!(
+            "{} - {:<14} {:<20} {:<18} {}",
+            g.start.with_timezone(&Local).format("%m-%d %H:%M:%S"),
+            end,
+            g.kind,
+            g.host,
+            g.detail,
+        );
+    }
+
+    Ok(())
+}
+
+async fn run_lock(proxy: &DaemonContextProxy<'_>, as_json: bool) -> CliResult {
+    proxy.lock().await?;
+    print_lock_state(true, as_json);
+    Ok(())
+}
+
+async fn run_unlock(proxy: &DaemonContextProxy<'_>, reason: String, args: PasswordArgs, as_json: bool) -> CliResult {
+    if !proxy.is_locked().await? {
+        return Err("The daemon is not locked".into());
+    }
+
+    let prompt = if proxy.has_partner_mode().await? {
+        "Password or unlock code: "
+    } else {
+        "Password: "
+    };
+    let credential = read_password(&args, prompt)?;
+    let delay = proxy.unlock(credential, reason).await?;
+    if delay == 0 {
+        print_lock_state(false, as_json);
+    } else if as_json {
+        println!("{}", json!({ "locked": true, "delay": delay }));
+    } else {
+        println!("The daemon unlocks after the cooling-off period, in {}.", fmt_duration(delay));
+    }
+    Ok(())
+}
+
+async fn run_password(proxy: &DaemonContextProxy<'_>, cmd: PasswordCommand) -> CliResult {
+    let prompt = PasswordArgs { password_stdin: false };
+    match cmd {
+        PasswordCommand::Set => {
+            let old_password = if proxy.has_password().await? {
+                read_password(&prompt, "Current password: ")?
+            } else {
+                String::new()
+            };
+            let new_password = read_password(&prompt, "New password: ")?;
+            if read_password(&prompt, "Confirm password: ")? != new_password {
+                return Err("The passwords do not match".into());
+            }
+
+            proxy.change_password(old_password, new_password).await?;
+            println!("The password was changed.");
+        },
+        PasswordCommand::Clear(args) => {
+            let password = read_password(&args, "Password: ")?;
+            proxy.clear_password(password).await?;
+            println!("The password was removed.");
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_partner(proxy: &DaemonContextProxy<'_>, cmd: PartnerCommand, as_json: bool) -> CliResult {
+    match cmd {
+        PartnerCommand::Enable(args) => {
+            let password = read_password(&args, "Password: ")?;
+            let url = proxy.enable_partner_mode(password).await?;
+            if as_json {
+                println!("{}", json!({ "url": url }));
+            } else {
+                println!("Share this secret with your partner, i.e. as a QR code in an authenticator app:");
+                println!("{}", url);
+            }
+        },
+        PartnerCommand::Disable(args) => {
+            let password = read_password(&args, "Password: ")?;
+            proxy.disable_partner_mode(password).await?;
+            println!("Partner mode was disabled.");
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_pending(proxy: &DaemonContextProxy<'_>, cmd: PendingCommand, as_json: bool) -> CliResult {
+    match cmd {
+        PendingCommand::List => {
+            let pending = proxy.get_pending_changes().await?;
+            if as_json {
+                println!("{}", serde_json::to_string_pretty(&pending)?);
+                return Ok(());
+            }
+
+            if pending.unlock_pending {
+                println!("{:>4} unlock at {}", "-", pending.unlock_at.with_timezone(&Local).format("%H:%M:%S"));
+            }
+            if pending.cooling_off_pending {
+                println!(
+                    "{:>4} shorten the cooling-off period to {} minutes at {}",
+                    "-",
+                    pending.cooling_off_minutes,
+                    pending.cooling_off_at.with_timezone(&Local).format("%H:%M:%S"),
+                );
+            }
+            if pending.day_start_pending {
+                println!(
+                    "{:>4} move the day start to {}:00 at {}",
+                    "-",
+                    pending.day_start_hour,
+                    pending.day_start_at.with_timezone(&Local).format("%H:%M:%S"),
+                );
+            }
+            for c in &pending.timer_changes {
+                let change = match c.action {
+                    TimerAction::Update => format!(
+                        "update '{}' to {} on {}",
+                        c.timer.display_name,
+                        fmt_duration(c.timer.time_limit),
+                        fmt_days(&c.timer.allowed_days),
+                    ),
+                    TimerAction::Delete => format!("delete '{}'", c.timer.display_name),
+                };
+                println!("{:>4} {} at {}", c.id, change, c.apply_at.with_timezone(&Local).format("%H:%M:%S"));
+            }
+        },
+        PendingCommand::Cancel { id } => {
+            proxy.cancel_timer_change(id).await?;
+            println!("Cancelled the pending change {}.", id);
+        },
+        PendingCommand::CancelUnlock => {
+            proxy.cancel_unlock().await?;
+            println!("Cancelled the pending unlock.");
+        },
+    }
+
+    Ok(())
+}
+
+async fn run_unlocks(proxy: &DaemonContextProxy<'_>, since: Option<NaiveDate>, as_json: bool) -> CliResult {
+    let day_start_hour = proxy.day_start_hour().await?;
+    let since = since.unwrap_or_else(|| day::tracking_day(Utc::now(), day_start_hour) - Days::new(7));
+    let events = proxy.get_unlock_events(day::day_start(since, day_start_hour), Utc::now()).await?;
+    if as_json {
+        println!("{}", serde_json::to_string_pretty(&events)?);
+        return Ok(());
+    }
+
+    for e in &events {
+        println!(
+            "{} {:<12} {}",
+            e.timestamp.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            e.method,
+            e.reason,
+        );
+    }
+
+    Ok(())
+}
+
+/// Fetch the timer for a given display name from the daemon.
+async fn find_timer(proxy: &DaemonContextProxy<'_>, display_name: &str, host: &Host) -> Result<Timer, Box<dyn Error>> {
+    proxy.get_daemon_snapshot().await?
+        .timers
+        .into_iter()
+        .find(|t| t.display_name == display_name && t.host == *host)
+        .ok_or_else(|| format!("No timer exists for '{}' on {}", display_name, host).into())
+}
+
+/// While the daemon is locked, authenticate this connection for an edit session.
+async fn ensure_can_edit(proxy: &DaemonContextProxy<'_>, args: &PasswordArgs) -> CliResult {
+    if proxy.is_locked().await? {
+        let password = read_password(args, "Password: ")?;
+        proxy.begin_edit_session(password).await?;
+    }
+
+    Ok(())
+}
+
+fn read_password(args: &PasswordArgs, prompt: &str) -> io::Result<String> {
+    if args.password_stdin {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    } else {
+        rpassword::prompt_password(prompt)
+    }
+}
+
+/// Report a timer change. A positive `delay` means the change waits out the cooling-off period.
+fn print_timer(action: &str, timer: &Timer, delay: u32, as_json: bool) -> CliResult {
+    if as_json {
+        println!("{}", json!({ "timer": timer, "delay": delay }));
+    } else if delay > 0 {
+        println!(
+            "{} the timer for '{}' after the cooling-off period, in {}.",
+            action, timer.display_name, fmt_duration(delay),
+        );
+    } else {
+        println!("{} the timer for '{}'.", action, timer.display_name);
+    }
+
+    Ok(())
+}
+
+fn print_lock_state(is_locked: bool, as_json: bool) {
+    if as_json {
+        println!("{}", json!({ "locked": is_locked }));
+    } else if is_locked {
+        println!("Locked.");
+    } else {
+        println!("Unlocked.");
+    }
+}
+
+/// Convert a list of weekdays into the Sunday-first representation of a `Timer`.
+fn days_to_mask(days: Option<&[Weekday]>) -> Vec<bool> {
+    match days {
+        Some(days) => (0..7)
+            .map(|i| days.iter().any(|d| d.num_days_from_sunday() as usize == i))
+            .collect(),
+        None => vec![true; 7],
+    }
+}
+
+fn fmt_days(allowed_days: &[bool]) -> String {
+    const NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    NAMES.iter()
+        .zip(allowed_days)
+        .filter(|&(_, &allowed)| allowed)
+        .map(|(name, _)| *name)
+        .collect::<Vec<&str>>()
+        .join(",")
+}
+
+fn fmt_duration(secs: u32) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+}
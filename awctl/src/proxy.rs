@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use zbus::{fdo, proxy};
+use shared::types::schema::{FocusChange, Timer};
+use shared::types::daemon::{ActiveSession, Coverage, DaemonSnapshot, DurationMap, PendingChanges, UnlockEvent};
+
+#[proxy(interface = "com.activity_warden.DaemonContext")]
+pub trait DaemonContext {
+    fn get_daemon_snapshot(&self) -> fdo::Result<DaemonSnapshot>;
+    fn get_status(&self) -> fdo::Result<Vec<ActiveSession>>;
+    fn get_sessions(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<Vec<FocusChange>>;
+    fn get_coverage(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<Coverage>;
+    fn get_durations(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<DurationMap>;
+    fn insert_timer(&self, timer: Timer) -> fdo::Result<()>;
+    fn delete_timer(&self, timer: Timer) -> fdo::Result<u32>;
+    fn update_timer(&self, timer: Timer) -> fdo::Result<u32>;
+    fn begin_edit_session(&self, password: String) -> fdo::Result<u32>;
+    fn is_locked(&self) -> fdo::Result<bool>;
+    fn has_password(&self) -> fdo::Result<bool>;
+    fn lock(&self) -> fdo::Result<()>;
+    fn unlock(&self, credential: String, reason: String) -> fdo::Result<u32>;
+    fn change_password(&self, old_password: String, new_password: String) -> fdo::Result<()>;
+    fn clear_password(&self, password: String) -> fdo::Result<()>;
+    fn has_partner_mode(&self) -> fdo::Result<bool>;
+    fn enable_partner_mode(&self, password: String) -> fdo::Result<String>;
+    fn disable_partner_mode(&self, password: String) -> fdo::Result<()>;
+    fn get_pending_changes(&self) -> fdo::Result<PendingChanges>;
+    fn cancel_unlock(&self) -> fdo::Result<()>;
+    fn cancel_timer_change(&self, id: i64) -> fdo::Result<()>;
+    fn get_unlock_events(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<Vec<UnlockEvent>>;
+
+    #[zbus(property)]
+    fn day_start_hour(&self) -> zbus::Result<u32>;
+}
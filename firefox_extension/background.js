@@ -20,6 +20,11 @@ port.onMessage.addListener((msg) => {
       console.log(`Closing tab ${tab_id}`);
       browser.tabs.remove(tab_id).await;
       break
+    case "Announce":
+      // The daemon lost track of the focus, i.e. because it restarted.
+      currentTab = null;
+      updateActiveTab();
+      break;
   }
 });
 
@@ -46,6 +51,7 @@ function updateActiveTab() {
           event_type: "focus_change",
           tab_id: activeTab.id, 
           tab_name: activeTab.title, 
+          url: activeTab.url,
           display_name: url.hostname,
         };
 
@@ -2,9 +2,14 @@ mod messaging;
 mod types;
 mod watcher;
 
+use std::time::Duration;
+use futures_lite::stream::StreamExt;
 use zbus::connection::Builder;
-use zbus::{Result, proxy};
-use shared::types::{Event, EventType};
+use zbus::{Connection, Result, proxy};
+use zbus::fdo::DBusProxy;
+use zbus::names::WellKnownName;
+use shared::types::{Event, EventMetadata, EventType};
+use shared::types::event::HEARTBEAT_INTERVAL_SECS;
 use shared::dbus::{Host, Interface, DBus};
 use serde_json::json;
 
@@ -15,6 +20,9 @@ use crate::watcher::FirefoxWatcher;
 #[proxy(interface = "com.activity_warden.EventBus")]
 trait EventBus {
     async fn send_event_msg(&self, event: Event) -> Result<usize>;
+
+    #[zbus(signal)]
+    fn focus_requested(&self) -> Result<()>;
 }
 
 #[tokio::main]
@@ -27,6 +35,11 @@ async fn main() -> Result<()> {
         .build()
         .await?;
 
+    // Start the User Daemon through D-Bus activation, unless it is running already.
+    if let Err(e) = start_user_daemon(&conn).await {
+        eprintln!("Failed to start the User Daemon: {}", e);
+    }
+
     // Open up a connection to the EventBus to transmit messages to the User Daemon.
     let proxy = EventBusProxy::builder(&conn)
         .destination(DBus::host_name(&Host::UserDaemon))?
@@ -34,6 +47,27 @@ async fn main() -> Result<()> {
         .build()
         .await?;
 
+    // Let the daemon know that tracking is active, even while the focus is unchanged.
+    let heartbeat_proxy = proxy.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            let _ = heartbeat_proxy.send_event_msg(status_event(EventType::Heartbeat)).await;
+        }
+    });
+
+    // Ask the extension to announce its focus again whenever the daemon requests it,
+    // i.e. after a restart in which the daemon lost the session in progress.
+    let mut focus_requests = proxy.receive_focus_requested().await?;
+    tokio::spawn(async move {
+        while focus_requests.next().await.is_some() {
+            let _ = write_message(&json!({
+                "type": MessageType::Announce,
+            }));
+        }
+    });
+
     // Listen to messages from the extension.
     while let Some(input) = read_message() {
         let reply = json!({
@@ -47,24 +81,18 @@ async fn main() -> Result<()> {
         let event_type = input.get("event_type").unwrap().as_str().unwrap();
         let event = match event_type {
             "focus_change" => {
-                let tab_id = input.get("tab_id").unwrap();
+                let tab_id = input.get("tab_id").unwrap().as_i64().unwrap();
                 let display_name = input.get("display_name").unwrap();
+                let text = |key: &str| input.get(key).and_then(|v| v.as_str()).unwrap_or("");
 
                 Event {
                     event_type: EventType::FocusChange,
                     source: Host::FirefoxWatcher,
                     display_name: display_name.as_str().unwrap().to_string(),
-                    metadata: tab_id.to_string(),
-                }
-            },
-            "focus_lost" => {
-                Event {
-                    event_type: EventType::FocusLost,
-                    source: Host::FirefoxWatcher,
-                    display_name: "".to_string(),
-                    metadata: "".to_string(),
+                    metadata: EventMetadata::tab(tab_id, text("url"), text("tab_name")),
                 }
             },
+            "focus_lost" => status_event(EventType::FocusLost),
             _ => {
                 panic!("Unexpected event type: {}", event_type);
             }
@@ -73,5 +101,25 @@ async fn main() -> Result<()> {
         let _ = proxy.send_event_msg(event).await;
     }
 
+    // The extension closed the connection, i.e. because the browser closed.
+    let _ = proxy.send_event_msg(status_event(EventType::Disconnect)).await;
+    Ok(())
+}
+
+/// Ask the bus to start the User Daemon, which is a no-op while it runs.
+async fn start_user_daemon(conn: &Connection) -> Result<()> {
+    let dbus = DBusProxy::new(conn).await?;
+    let name = WellKnownName::try_from(DBus::host_name(&Host::UserDaemon))?;
+    dbus.start_service_by_name(name, 0).await?;
     Ok(())
 }
+
+/// Build an event that carries no display name.
+fn status_event(event_type: EventType) -> Event {
+    Event {
+        event_type,
+        source: Host::FirefoxWatcher,
+        display_name: "".to_string(),
+        metadata: EventMetadata::default(),
+    }
+}
@@ -18,7 +18,9 @@ pub fn read_message() -> Option<Value> {
 pub fn write_message(message: &Value) -> io::Result<()> {
     let json_bytes = serde_json::to_vec(message)?;
     let len = (json_bytes.len() as u32).to_le_bytes();
-    io::stdout().write_all(&len)?;
-    io::stdout().write_all(&json_bytes)?;
-    io::stdout().flush()
+    // Hold the lock for the whole message, so that concurrent writers do not interleave.
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&len)?;
+    stdout.write_all(&json_bytes)?;
+    stdout.flush()
 }
@@ -1,7 +1,9 @@
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum MessageType {
     Close,
+    Announce,
     ACK,
 }
\ No newline at end of file
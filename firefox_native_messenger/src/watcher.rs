@@ -10,11 +10,11 @@ impl FirefoxWatcher {
 
 #[interface(name = "com.activity_warden.Watcher")]
 impl FirefoxWatcher {
-    async fn request_close(&self, metadata: String) -> zbus::fdo::Result<()> {
-        // Construct the metadata required to close a tab on timeout.
+    async fn request_close(&self, tab_id: i64) -> zbus::fdo::Result<()> {
+        // Ask the extension to close the tab on timeout.
         let msg = json!({
             "type": MessageType::Close,
-            "tab_id": metadata
+            "tab_id": tab_id
         });
         write_message(&msg).unwrap();
 
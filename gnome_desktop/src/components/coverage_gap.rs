@@ -0,0 +1,50 @@
+use relm4::adw;
+use relm4::adw::prelude::*;
+use relm4::gtk;
+use relm4::prelude::*;
+use relm4::factory::DynamicIndex;
+use chrono::Local;
+
+use shared::types::daemon::{CoverageGap, GapKind};
+
+pub struct CoverageGapModel {
+    gap: CoverageGap,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for CoverageGapModel {
+    type Init = CoverageGap;
+    type Input = ();
+    type Output = ();
+    type CommandOutput = ();
+    type ParentWidget = gtk::ListBox;
+    type Index = DynamicIndex;
+
+    view! {
+        #[root]
+        adw::ActionRow {
+            set_title: &format!("{}: {}", describe(self.gap.kind), self.gap.detail),
+            set_subtitle: &format!(
+                "{} - {}",
+                self.gap.start.with_timezone(&Local).format("%H:%M"),
+                if self.gap.ongoing {
+                    "now".to_string()
+                } else {
+                    self.gap.end.with_timezone(&Local).format("%H:%M").to_string()
+                },
+            ),
+        }
+    }
+
+    fn init_model(gap: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        CoverageGapModel { gap }
+    }
+}
+
+fn describe(kind: GapKind) -> &'static str {
+    match kind {
+        GapKind::DaemonDown => "Daemon not running",
+        GapKind::WatcherDisconnected => "Watcher disconnected",
+        GapKind::ClockJump => "Clock changed",
+    }
+}
@@ -8,6 +8,7 @@ pub struct HeaderModel {}
 pub enum HeaderModelOutput {
     Timers,
     Data,
+    Status,
     Settings
 }
 
@@ -44,6 +45,16 @@ impl SimpleComponent for HeaderModel {
                         }
                     }
                 },
+                gtk::ToggleButton {
+                    set_label: "Status",
+                    set_group: Some(&group),
+                    set_active: false,
+                    connect_toggled[sender] => move |btn| {
+                        if btn.is_active() {
+                            sender.output(HeaderModelOutput::Status).unwrap();
+                        }
+                    }
+                },
                 gtk::ToggleButton {
                     set_label: "Settings",
                     set_group: Some(&group),
@@ -1,3 +1,5 @@
 pub mod header_model;
 pub mod timer_popup;
-pub mod timer_display;
\ No newline at end of file
+pub mod timer_display;
+pub mod pending_change;
+pub mod coverage_gap;
\ No newline at end of file
@@ -0,0 +1,64 @@
+use relm4::adw;
+use relm4::adw::prelude::*;
+use relm4::gtk;
+use relm4::prelude::*;
+use relm4::factory::DynamicIndex;
+use chrono::Local;
+
+use shared::types::daemon::{PendingTimerChange, TimerAction};
+
+#[derive(Debug)]
+pub enum PendingChangeOutput {
+    /// Withdraw the pending change with the given id.
+    Cancel(i64),
+}
+
+pub struct PendingChangeModel {
+    change: PendingTimerChange,
+}
+
+#[relm4::factory(pub)]
+impl FactoryComponent for PendingChangeModel {
+    type Init = PendingTimerChange;
+    type Input = ();
+    type Output = PendingChangeOutput;
+    type CommandOutput = ();
+    type ParentWidget = gtk::ListBox;
+    type Index = DynamicIndex;
+
+    view! {
+        #[root]
+        adw::ActionRow {
+            set_title: &describe(&self.change),
+            set_subtitle: &format!(
+                "Applies at {}",
+                self.change.apply_at.with_timezone(&Local).format("%H:%M"),
+            ),
+
+            add_suffix = &gtk::Button {
+                set_label: "Cancel",
+                set_valign: gtk::Align::Center,
+                add_css_class: "flat",
+
+                connect_clicked[sender, id = self.change.id] => move |_| {
+                    let _ = sender.output(PendingChangeOutput::Cancel(id));
+                },
+            },
+        }
+    }
+
+    fn init_model(change: Self::Init, _index: &DynamicIndex, _sender: FactorySender<Self>) -> Self {
+        PendingChangeModel { change }
+    }
+}
+
+fn describe(change: &PendingTimerChange) -> String {
+    match change.action {
+        TimerAction::Update => format!(
+            "Allow {} minutes on {}",
+            change.timer.time_limit / 60,
+            change.timer.display_name,
+        ),
+        TimerAction::Delete => format!("Delete the timer for {}", change.timer.display_name),
+    }
+}
@@ -4,13 +4,38 @@ use relm4::gtk;
 use relm4::prelude::*;
 use relm4::factory::DynamicIndex;
 use chrono::Datelike;
+use std::time::Instant;
 
+use shared::types::daemon::ActiveSession;
 use shared::types::schema::Timer;
 use crate::DurationId;
 
+/// A session in progress, as last reported by the daemon.
+#[derive(Debug, Clone)]
+pub struct LiveSession {
+    pub session: ActiveSession,
+    /// The moment of the report, from which the session keeps counting.
+    pub reported_at: Instant,
+}
+
+impl LiveSession {
+    /// The seconds that the session accrued since it was reported.
+    fn elapsed(&self) -> u32 {
+        if self.session.counting {
+            self.reported_at.elapsed().as_secs() as u32
+        } else {
+            0
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum TimerDisplayInput {
     UpdateDuration(DurationId),
+    /// Replace the sessions in progress, of which at most one belongs to this timer.
+    UpdateLiveSessions(Vec<LiveSession>),
+    /// Redraw the countdown of the session in progress.
+    Tick,
 }
 
 #[derive(Debug)]
@@ -22,12 +47,14 @@ pub enum TimerDisplayOutput {
 pub struct TimerDisplayModel {
     timer: Timer,
     duration: usize,
+    live: Option<LiveSession>,
     index: DynamicIndex,
 }
 
 pub struct TimerInit {
     pub timer: Timer,
-    pub duration: usize
+    pub duration: usize,
+    pub live: Option<LiveSession>,
 }
 
 #[relm4::factory(pub)]
@@ -60,7 +87,7 @@ impl FactoryComponent for TimerDisplayModel {
                     #[watch]
                     set_subtitle: &format!(
                         "{} / {}",
-                        fmt_mm_ss(self.duration as u32),
+                        fmt_mm_ss(self.current_duration()),
                         fmt_timer_duration(&self.timer),
                     ),
 
@@ -97,6 +124,16 @@ impl FactoryComponent for TimerDisplayModel {
                         set_hexpand: false,
                         set_vexpand: false,
 
+                        // Countdown of the session in progress:
+                        gtk::Label {
+                            add_css_class: "numeric",
+                            add_css_class: "accent",
+                            #[watch]
+                            set_visible: self.live.is_some(),
+                            #[watch]
+                            set_label: &self.countdown(),
+                        },
+
                         // Edit button:
                         gtk::Button {
                             set_tooltip_text: Some("Edit timer"),
@@ -150,6 +187,7 @@ impl FactoryComponent for TimerDisplayModel {
         TimerDisplayModel {
             timer: init.timer,
             duration: init.duration,
+            live: init.live,
             index: index.clone(),
         }
     }
@@ -161,6 +199,38 @@ impl FactoryComponent for TimerDisplayModel {
                     self.duration = dur_id.duration;
                 }
             }
+            TimerDisplayInput::UpdateLiveSessions(sessions) => {
+                self.live = sessions.into_iter().find(|live| {
+                    live.session.host == self.timer.host && live.session.display_name == self.timer.display_name
+                });
+            }
+            // The view recomputes the countdown from the elapsed time.
+            TimerDisplayInput::Tick => { }
+        }
+    }
+}
+
+impl TimerDisplayModel {
+    /// The seconds spent today, counting the session in progress up to now.
+    fn current_duration(&self) -> u32 {
+        match &self.live {
+            Some(live) => live.session.duration + live.elapsed(),
+            None => self.duration as u32,
+        }
+    }
+
+    /// Describe the time left to the session in progress.
+    fn countdown(&self) -> String {
+        let Some(live) = &self.live else {
+            return String::new();
+        };
+
+        if !live.session.counting {
+            "Paused".to_string()
+        } else if live.session.has_timer {
+            format!("{} left", fmt_mm_ss(live.session.remaining.saturating_sub(live.elapsed())))
+        } else {
+            "Active".to_string()
         }
     }
 }
@@ -11,18 +11,22 @@ use relm4::{gtk, ComponentController};
 use relm4::gtk::prelude::*;
 use relm4::prelude::*;
 use shared::dbus::{DBus, Host, Interface};
-use futures_util::stream::StreamExt;
+use futures_util::future;
+use futures_util::stream::{self, BoxStream, StreamExt};
 use zbus::Connection;
 use zbus::blocking::{connection::Builder, Connection as BlockingConnection};
 use shared::types::schema::FocusChange;
-use shared::types::daemon::DurationMap;
+use shared::types::daemon::{ActiveSession, DurationMap};
+use shared::types::day;
+use chrono::{Local, NaiveDate, Utc};
 
 use crate::components::header_model::{HeaderModel, HeaderModelOutput};
 use crate::proxy::DaemonContextProxy;
 use crate::proxy::DaemonContextProxyBlocking;
 use crate::pages::home::{HomeInit, HomePage, HomeInput};
 use crate::pages::data::{DataInit, DataPage, DataInput};
-use crate::pages::settings::{SettingsInit, SettingsOut, SettingsPage};
+use crate::pages::settings::{SettingsInit, SettingsInput, SettingsOut, SettingsPage};
+use crate::pages::status::{StatusInit, StatusInput, StatusPage};
 use crate::pages::Page;
 
 
@@ -45,6 +49,14 @@ enum AWMsg {
     DurationUpdate(FocusChange),
     /// Change whether or not the application is locked.
     LockStatusChange(bool),
+    /// The daemon was locked or unlocked, possibly by another client.
+    ReloadLockStatus,
+    /// The pending changes were queued, cancelled or applied.
+    PendingChangesChanged,
+    /// The configuration of the daemon was reloaded or replaced.
+    ConfigChanged,
+    /// A session started, ended or paused, or its remaining time changed.
+    ActiveSessionsChanged(Vec<ActiveSession>),
     /// Load the initial durations to populate state.
     LoadDurations,
     /// A non operation for pages without output messages.
@@ -53,7 +65,8 @@ enum AWMsg {
 
 #[derive(Debug)]
 enum AWCommandMsg {
-    DurationsLoaded(DurationMap)
+    /// The durations of the current day, which starts at the given hour.
+    DurationsLoaded(DurationMap, u32)
 }
 
 struct AWModelInit {
@@ -67,11 +80,15 @@ struct AWModel {
     header: Controller<HeaderModel>,
     home: Controller<HomePage>,
     data: Controller<DataPage>,
+    status: Controller<StatusPage>,
     settings: Controller<SettingsPage>,
 
     // Internal State:
     timer_durations: Rc<RefCell<DurationMap>>,
     is_locked: Rc<RefCell<bool>>,
+    /// The hour at which the daemon starts a new day, and the day that the durations belong to.
+    day_start_hour: u32,
+    cur_day: NaiveDate,
 
     // DBus Proxies:
     dbus_conn: BlockingConnection, 
@@ -101,6 +118,7 @@ impl Component for AWModel {
 
                     add_named[Some("timers")] = model.home.widget(),
                     add_named[Some("data")] = model.data.widget(),
+                    add_named[Some("status")] = model.status.widget(),
                     add_named[Some("settings")] = model.settings.widget(),
                     
                     // Determine which page in the stack should be visible.
@@ -126,6 +144,7 @@ impl Component for AWModel {
             .forward(sender.input_sender(), |msg| match msg {
                 HeaderModelOutput::Timers => AWMsg::SetMode(Page::Timers),
                 HeaderModelOutput::Data => AWMsg::SetMode(Page::Data),
+                HeaderModelOutput::Status => AWMsg::SetMode(Page::Status),
                 HeaderModelOutput::Settings => AWMsg::SetMode(Page::Settings),
             });
 
@@ -150,6 +169,12 @@ impl Component for AWModel {
             })
             .forward(sender.input_sender(), |_o| AWMsg::NoOp);
 
+        let status = StatusPage::builder()
+            .launch(StatusInit {
+                dbus_conn: params.dbus_conn.clone(),
+            })
+            .forward(sender.input_sender(), |_o| AWMsg::NoOp);
+
         let settings = SettingsPage::builder()
             .launch(SettingsInit {
                 dbus_conn: params.dbus_conn.clone(),
@@ -163,24 +188,17 @@ impl Component for AWModel {
         let list_box = gtk::ListBox::new();
         list_box.set_selection_mode(gtk::SelectionMode::None);
 
-        // Spawn the thread to listen for duration updates from the daemon.
-        let signal_sender = sender.clone();
-        relm4::tokio::spawn(async move {
-            let conn = Connection::session().await.unwrap();
-
-            let proxy = DaemonContextProxy::builder(&conn)
-                .destination(DBus::host_name(&Host::UserDaemon)).unwrap()
-                .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext)).unwrap()
-                .build().await.unwrap();
-
-            let mut stream = proxy.receive_duration_changed().await.unwrap();
-            while let Some(sig) = stream.next().await {
-                let args = sig.args().unwrap();
-                let changes: &FocusChange = args.change();
-                signal_sender.input(AWMsg::DurationUpdate(changes.clone()));
-            }
-        });
-        
+        // Spawn a thread per signal of the daemon, each surviving a restart of the daemon.
+        for signal in [
+            DaemonSignal::DurationChanged,
+            DaemonSignal::LockStateChanged,
+            DaemonSignal::PendingChangesChanged,
+            DaemonSignal::ConfigChanged,
+            DaemonSignal::ActiveSessionsChanged,
+        ] {
+            relm4::tokio::spawn(follow_signal(signal, sender.clone()));
+        }
+
         // Load the initial timer durations.
         sender.input(AWMsg::LoadDurations);
 
@@ -189,9 +207,12 @@ impl Component for AWModel {
             header, 
             home,
             data,
+            status,
             settings,
             timer_durations,
             is_locked,
+            day_start_hour: 0,
+            cur_day: Local::now().date_naive(),
             dbus_conn: params.dbus_conn,
         };
 
@@ -210,9 +231,19 @@ impl Component for AWModel {
                 relm4::main_adw_application().quit();
             },
             AWMsg::SetMode(mode) => {
+                // The coverage changes continuously, so refresh it whenever it is shown.
+                if mode == Page::Status {
+                    self.status.sender().send(StatusInput::LoadCoverage).unwrap();
+                }
                 self.page = mode;
             },
             AWMsg::DurationUpdate(fc) => {
+                // A session of a new day starts the durations over.
+                if day::tracking_day(fc.timestamp, self.day_start_hour) > self.cur_day {
+                    sender.input(AWMsg::LoadDurations);
+                    return;
+                }
+
                 // Update the complete set of durations maintained per host.
                 let mut timer_durations = (*self.timer_durations).borrow_mut();
                 let host_map = timer_durations.entry(fc.host.clone()).or_default();
@@ -235,7 +266,24 @@ impl Component for AWModel {
             },
             AWMsg::LockStatusChange(is_locked) => {
                 (*self.is_locked).replace(is_locked);
-            }
+            },
+            AWMsg::ReloadLockStatus => {
+                self.settings.sender().send(SettingsInput::LoadLockStatus).unwrap();
+            },
+            AWMsg::PendingChangesChanged => {
+                // An applied change may have modified or removed a timer.
+                self.settings.sender().send(SettingsInput::LoadLockStatus).unwrap();
+                self.home.sender().send(HomeInput::LoadTimers).unwrap();
+            },
+            AWMsg::ConfigChanged => {
+                self.settings.sender().send(SettingsInput::LoadConfig).unwrap();
+
+                // The day may start at another hour now.
+                sender.input(AWMsg::LoadDurations);
+            },
+            AWMsg::ActiveSessionsChanged(sessions) => {
+                self.home.sender().send(HomeInput::ActiveSessionsChanged(sessions)).unwrap();
+            },
             AWMsg::LoadDurations => {
                 let dbus_conn = self.dbus_conn.clone();
                 sender.spawn_oneshot_command(move || {
@@ -245,7 +293,8 @@ impl Component for AWModel {
                         .build().unwrap();
 
                     let snapshot = proxy.get_daemon_snapshot().unwrap();
-                    AWCommandMsg::DurationsLoaded(snapshot.durations)
+                    let day_start_hour = proxy.day_start_hour().unwrap_or(0);
+                    AWCommandMsg::DurationsLoaded(snapshot.durations, day_start_hour)
                 });
             },
             AWMsg::NoOp => { }
@@ -260,8 +309,10 @@ impl Component for AWModel {
         _: &Self::Root,
     ) {
         match message {
-            AWCommandMsg::DurationsLoaded(duration_map) => {
+            AWCommandMsg::DurationsLoaded(duration_map, day_start_hour) => {
                 (*self.timer_durations).replace(duration_map);
+                self.day_start_hour = day_start_hour;
+                self.cur_day = day::tracking_day(Utc::now(), day_start_hour);
 
                 self.data.sender().send(DataInput::DurationsLoaded).unwrap();
                 self.home.sender().send(HomeInput::DurationsLoaded).unwrap();
@@ -270,6 +321,83 @@ impl Component for AWModel {
     }
 }
 
+/// The signals of the daemon context that the application follows.
+#[derive(Clone, Copy, Debug)]
+enum DaemonSignal {
+    /// The durations of the timers changed.
+    DurationChanged,
+    /// The daemon was locked or unlocked, possibly by another client.
+    LockStateChanged,
+    /// The changes waiting out the cooling-off period changed.
+    PendingChangesChanged,
+    /// The configuration of the daemon was reloaded or replaced.
+    ConfigChanged,
+    /// The sessions in progress, which drive the live countdowns, changed.
+    ActiveSessionsChanged,
+}
+
+/// How long to wait before subscribing to a signal again after a failure.
+const RESUBSCRIBE_DELAY_SECS: u32 = 5;
+
+/// Forward a signal of the daemon to the application for as long as it runs.
+/// A failure to connect or to subscribe, i.e. because the daemon is not running
+/// or restarting, is logged and retried, as is a stream that ends.
+async fn follow_signal(signal: DaemonSignal, sender: ComponentSender<AWModel>) {
+    loop {
+        match subscribe(signal).await {
+            Ok(mut stream) => {
+                while let Some(msg) = stream.next().await {
+                    sender.input(msg);
+                }
+                eprintln!("The {signal:?} signal of the daemon ended, subscribing again");
+            },
+            Err(e) => eprintln!("Failed to subscribe to the {signal:?} signal of the daemon: {e}"),
+        }
+        gtk::glib::timeout_future_seconds(RESUBSCRIBE_DELAY_SECS).await;
+    }
+}
+
+/// Connect to the daemon and subscribe to a signal, mapping it to the message it
+/// sends to the application.
+async fn subscribe(signal: DaemonSignal) -> zbus::Result<BoxStream<'static, AWMsg>> {
+    let conn = Connection::session().await?;
+
+    let proxy = DaemonContextProxy::builder(&conn)
+        .destination(DBus::host_name(&Host::UserDaemon))?
+        .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext))?
+        .build().await?;
+
+    Ok(match signal {
+        DaemonSignal::DurationChanged => proxy.receive_duration_changed().await?
+            .filter_map(|sig| future::ready(match sig.args() {
+                Ok(args) => Some(AWMsg::DurationUpdate(args.change().clone())),
+                Err(e) => {
+                    eprintln!("Failed to read a duration change of the daemon: {e}");
+                    None
+                },
+            }))
+            .boxed(),
+        DaemonSignal::LockStateChanged => proxy.receive_lock_state_changed().await?
+            .map(|_| AWMsg::ReloadLockStatus)
+            .boxed(),
+        DaemonSignal::PendingChangesChanged => proxy.receive_pending_changes_changed().await?
+            .map(|_| AWMsg::PendingChangesChanged)
+            .boxed(),
+        DaemonSignal::ConfigChanged => proxy.receive_config_changed().await?
+            .map(|_| AWMsg::ConfigChanged)
+            .boxed(),
+        DaemonSignal::ActiveSessionsChanged => {
+            let changes = proxy.receive_active_sessions_changed().await
+                .filter_map(|change| async move { change.get().await.ok() });
+            let current = proxy.active_sessions().await.ok();
+            stream::iter(current)
+                .chain(changes)
+                .map(AWMsg::ActiveSessionsChanged)
+                .boxed()
+        },
+    })
+}
+
 fn main() {
     let dbus_conn = Builder::session().unwrap()
         .name(DBus::host_name(&Host::GnomeApplication)).unwrap()
@@ -4,15 +4,18 @@ use relm4::prelude::*;
 use relm4::gtk::prelude::*;
 use shared::types::schema::Timer;
 use shared::dbus::{DBus, Host, Interface};
-use shared::types::daemon::DurationMap;
+use shared::types::daemon::{ActiveSession, DurationMap};
 use zbus::blocking::Connection as BlockingConnection;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::time::Instant;
+use chrono::{Duration, Local};
 
 use crate::DurationId;
-use crate::components::timer_display::{TimerDisplayInput, TimerDisplayModel, TimerDisplayOutput, TimerInit};
+use crate::components::timer_display::{LiveSession, TimerDisplayInput, TimerDisplayModel, TimerDisplayOutput, TimerInit};
 use crate::components::timer_popup::{TimerPopupModel, TimerPopupInput, TimerPopupOutput};
+use crate::pages::settings::error_message;
 use crate::proxy::{DaemonContextProxyBlocking};
 
 #[derive(Debug)]
@@ -23,7 +26,11 @@ pub enum HomeInput {
     UpdateTimer(DynamicIndex, Timer),
     /// Transmit the old duration ID with the new duration delta.
     DurationUpdate(DurationId),
-    DurationsLoaded
+    DurationsLoaded,
+    /// The daemon reported the sessions in progress.
+    ActiveSessionsChanged(Vec<ActiveSession>),
+    /// Advance the countdowns of the sessions in progress.
+    Tick,
 }
 
 #[derive(Debug)]
@@ -32,6 +39,10 @@ pub enum HomeCmd {
     TimerCreated(Timer),
     TimerDeleted(usize),
     TimerUpdated(Timer, usize),
+    /// The daemon queued a modification for the cooling-off period of `u32` seconds.
+    ChangeQueued(u32),
+    /// The daemon refused a modification, i.e. because it is locked.
+    RequestRejected(String),
 }
 
 #[derive(Debug)]
@@ -55,6 +66,10 @@ pub struct HomePage {
     timers: Rc<RefCell<Vec<Timer>>>,
     timer_durations: Rc<RefCell<DurationMap>>,
     is_locked: Rc<RefCell<bool>>,
+    live_sessions: Vec<LiveSession>,
+    /// The outcome of the last modification that did not apply right away.
+    notice: Option<String>,
+    error: Option<String>,
 }
 
 #[relm4::component(pub)]
@@ -70,11 +85,43 @@ impl Component for HomePage {
             set_vexpand: true,
             set_hexpand: true,
 
-            // Timer List:
-            gtk::ScrolledWindow {
-                set_vexpand: true,
-                set_hexpand: true,
-                #[local_ref] timer_display_box -> gtk::ListBox { }
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+
+                // Queued Change Message:
+                gtk::Label {
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    set_margin_start: 12,
+                    set_margin_end: 12,
+                    set_margin_top: 12,
+                    add_css_class: "dim-label",
+                    #[watch]
+                    set_visible: model.notice.is_some(),
+                    #[watch]
+                    set_label: model.notice.as_deref().unwrap_or(""),
+                },
+
+                // Rejection Message:
+                gtk::Label {
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    set_margin_start: 12,
+                    set_margin_end: 12,
+                    set_margin_top: 12,
+                    add_css_class: "error",
+                    #[watch]
+                    set_visible: model.error.is_some(),
+                    #[watch]
+                    set_label: model.error.as_deref().unwrap_or(""),
+                },
+
+                // Timer List:
+                gtk::ScrolledWindow {
+                    set_vexpand: true,
+                    set_hexpand: true,
+                    #[local_ref] timer_display_box -> gtk::ListBox { }
+                },
             },
 
             // Overlay Button:
@@ -129,6 +176,9 @@ impl Component for HomePage {
             timers: init.timers,
             timer_durations: init.timer_durations,
             is_locked: init.is_locked,
+            live_sessions: Vec::new(),
+            notice: None,
+            error: None,
         };
 
         let timer_display_box = model.timer_factory.widget();
@@ -136,6 +186,13 @@ impl Component for HomePage {
 
         // Load the initial timers for the frontend.
         sender.input(HomeInput::LoadTimers);
+
+        // The daemon only reports changes, so the countdowns advance on their own in between.
+        let tick_sender = sender.clone();
+        gtk::glib::timeout_add_seconds_local(1, move || {
+            tick_sender.input(HomeInput::Tick);
+            gtk::glib::ControlFlow::Continue
+        });
         ComponentParts { model, widgets }
     }
 
@@ -144,6 +201,18 @@ impl Component for HomePage {
             HomeInput::DurationUpdate(dur_id) => {
                 self.timer_factory.broadcast(TimerDisplayInput::UpdateDuration(dur_id));
             },
+            HomeInput::ActiveSessionsChanged(sessions) => {
+                let reported_at = Instant::now();
+                self.live_sessions = sessions.into_iter()
+                    .map(|session| LiveSession { session, reported_at })
+                    .collect();
+                self.timer_factory.broadcast(TimerDisplayInput::UpdateLiveSessions(self.live_sessions.clone()));
+            },
+            HomeInput::Tick => {
+                if !self.live_sessions.is_empty() {
+                    self.timer_factory.broadcast(TimerDisplayInput::Tick);
+                }
+            },
             HomeInput::DurationsLoaded => {
                 let timers = self.timers.borrow();
                 let dur_map = self.timer_durations.borrow();
@@ -181,8 +250,10 @@ impl Component for HomePage {
                         .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext)).unwrap()
                         .build().unwrap();
 
-                    proxy.insert_timer(timer.clone()).unwrap();
-                    HomeCmd::TimerCreated(timer)
+                    match proxy.insert_timer(timer.clone()) {
+                        Ok(()) => HomeCmd::TimerCreated(timer),
+                        Err(e) => HomeCmd::RequestRejected(error_message(e)),
+                    }
                 });
             }
             HomeInput::DeleteTimer(idx) => {
@@ -197,8 +268,11 @@ impl Component for HomePage {
                             .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext)).unwrap()
                             .build().unwrap();
 
-                        proxy.delete_timer(del_timer).unwrap();
-                        HomeCmd::TimerDeleted(i)
+                        match proxy.delete_timer(del_timer) {
+                            Ok(0) => HomeCmd::TimerDeleted(i),
+                            Ok(delay) => HomeCmd::ChangeQueued(delay),
+                            Err(e) => HomeCmd::RequestRejected(error_message(e)),
+                        }
                     });
                 }
             }
@@ -213,8 +287,11 @@ impl Component for HomePage {
                             .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext)).unwrap()
                             .build().unwrap();
 
-                        proxy.update_timer(timer.clone()).unwrap();
-                        HomeCmd::TimerUpdated(timer, i)
+                        match proxy.update_timer(timer.clone()) {
+                            Ok(0) => HomeCmd::TimerUpdated(timer, i),
+                            Ok(delay) => HomeCmd::ChangeQueued(delay),
+                            Err(e) => HomeCmd::RequestRejected(error_message(e)),
+                        }
                     });
                 }
             }
@@ -243,6 +320,7 @@ impl Component for HomePage {
                     let init = TimerInit {
                         timer: t.clone(),
                         duration: *duration as usize,
+                        live: live_session(&self.live_sessions, t),
                     };
                     guard.push_back(init);
                 }
@@ -258,16 +336,21 @@ impl Component for HomePage {
                     .get(&timer.display_name)
                     .unwrap_or(&0);
                 let init = TimerInit {
+                    live: live_session(&self.live_sessions, &timer),
                     timer,
                     duration: *duration as usize,
                 };
                 self.timer_factory.guard().push_back(init);
             }
             HomeCmd::TimerDeleted(i) => {
+                self.notice = None;
+                self.error = None;
                 (*self.timers).borrow_mut().remove(i);
                 self.timer_factory.guard().remove(i);
             }
             HomeCmd::TimerUpdated(timer, i) => {
+                self.notice = None;
+                self.error = None;
                 (*self.timers).borrow_mut()[i] = timer.clone();
                 let mut g = self.timer_factory.guard();
                 g.remove(i);
@@ -279,11 +362,31 @@ impl Component for HomePage {
                     .get(&timer.display_name)
                     .unwrap_or(&0);
                 let init = TimerInit {
+                    live: live_session(&self.live_sessions, &timer),
                     timer,
                     duration: *duration as usize,
                 };
                 g.insert(i, init);
             }
+            HomeCmd::ChangeQueued(delay) => {
+                let apply_at = Local::now() + Duration::seconds(delay as i64);
+                self.notice = Some(format!(
+                    "The change applies after the cooling-off period, at {}. It can be cancelled in the settings.",
+                    apply_at.format("%H:%M"),
+                ));
+                self.error = None;
+            }
+            HomeCmd::RequestRejected(msg) => {
+                self.notice = None;
+                self.error = Some(msg);
+            }
         }
     }
-}
\ No newline at end of file
+}
+
+/// The session in progress on the display name of a timer, if any.
+fn live_session(live_sessions: &[LiveSession], timer: &Timer) -> Option<LiveSession> {
+    live_sessions.iter()
+        .find(|live| live.session.host == timer.host && live.session.display_name == timer.display_name)
+        .cloned()
+}
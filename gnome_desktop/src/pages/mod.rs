@@ -1,6 +1,7 @@
 pub mod settings;
 pub mod data;
 pub mod home;
+pub mod status;
 
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -9,6 +10,8 @@ pub enum Page {
     Timers,
     /// Display a graph of the current time spent on websites.
     Data,
+    /// Display how much of the day was tracked.
+    Status,
     /// Provide a place to change the locking password.
     Settings,
 }
@@ -18,6 +21,7 @@ impl Page {
         match self {
             Page::Timers => "timers",
             Page::Data => "data",
+            Page::Status => "status",
             Page::Settings => "settings",
         }
     }
@@ -2,15 +2,34 @@ use relm4::{Component, ComponentSender, ComponentParts};
 use relm4::gtk;
 use relm4::gtk::prelude::*;
 use relm4::adw::prelude::*;
+use relm4::prelude::*;
+use chrono::{DateTime, Local, Utc};
 use zbus::blocking::Connection as BlockingConnection;
+use zbus::fdo;
 
 use shared::dbus::{DBus, Host, Interface};
+use shared::types::daemon::PendingChanges;
+use crate::components::pending_change::{PendingChangeModel, PendingChangeOutput};
 use crate::proxy::DaemonContextProxyBlocking;
 
-#[derive(Debug)]
 pub struct SettingsPage {
     dbus_conn: BlockingConnection,
     is_locked: bool,
+    has_password: bool,
+    /// Whether a partner code is accepted in place of the password.
+    has_partner_mode: bool,
+    /// The moment at which a requested unlock applies, if one is pending.
+    unlock_at: Option<DateTime<Utc>>,
+    /// The requested cooling-off period in minutes and the moment at which it applies, if one is pending.
+    cooling_off_at: Option<(u32, DateTime<Utc>)>,
+    /// The requested day start hour and the moment at which it applies, if one is pending.
+    day_start_at: Option<(u32, DateTime<Utc>)>,
+    /// The timer changes waiting out the cooling-off period.
+    pending_factory: FactoryVecDeque<PendingChangeModel>,
+    /// The contents of the configuration file being edited.
+    config_buffer: gtk::TextBuffer,
+    /// The reason the last request was rejected, if any.
+    error: Option<String>,
 }
 
 #[derive(Debug)]
@@ -20,15 +39,37 @@ pub enum SettingsOut {
 
 #[derive(Debug)]
 pub enum SettingsInput {
-    /// Submit a newly entered password to the backend.
-    SubmitPassword,
-    /// Query the daemon to determine if there is a current password.
+    /// Lock the daemon, or unlock it with the entered password if it is locked.
+    ToggleLock,
+    /// Set a new password, or replace the current one.
+    ChangePassword,
+    /// Remove the current password, which also unlocks the daemon.
+    ClearPassword,
+    /// Withdraw the pending unlock.
+    CancelUnlock,
+    /// Withdraw a pending timer change.
+    CancelTimerChange(i64),
+    /// Query the daemon for the lock state, whether a password is set and the pending changes.
     LoadLockStatus,
+    /// Query the daemon for the contents of its configuration file.
+    LoadConfig,
+    /// Ask the daemon to read its configuration file again, discarding any edits.
+    ReloadConfig,
+    /// Replace the configuration file with the edited contents.
+    ApplyConfig,
 }
 
 #[derive(Debug)]
 pub enum SettingsCmd {
-    LockStatusLoaded(bool)
+    LockStatusLoaded {
+        is_locked: bool,
+        has_password: bool,
+        has_partner_mode: bool,
+        pending: PendingChanges,
+    },
+    ConfigLoaded(String),
+    /// The daemon refused the request, i.e. due to a wrong password.
+    RequestRejected(String),
 }
 
 #[derive(Debug)]
@@ -45,62 +86,284 @@ impl Component for SettingsPage {
 
     view! {
         #[root]
-        gtk::Box {
-            set_orientation: gtk::Orientation::Vertical,
-            set_spacing: 14,
-            set_margin_top: 18,
-            set_margin_bottom: 18,
-            set_margin_start: 18,
-            set_margin_end: 18,
-
-            // Header:
+        gtk::ScrolledWindow {
+            set_hscrollbar_policy: gtk::PolicyType::Never,
+
             gtk::Box {
                 set_orientation: gtk::Orientation::Vertical,
-                set_spacing: 6,
+                set_spacing: 14,
+                set_margin_top: 18,
+                set_margin_bottom: 18,
+                set_margin_start: 18,
+                set_margin_end: 18,
+
+                // Lock Header:
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 6,
+
+                    gtk::Label {
+                        set_xalign: 0.0,
+                        set_label: "Session Lock",
+                        add_css_class: "title-2",
+                    },
+
+                    gtk::Label {
+                        set_xalign: 0.0,
+                        set_wrap: true,
+                        #[watch]
+                        set_label: if model.is_locked && model.has_partner_mode {
+                            "The session is locked. Enter your password or a code from your partner, along with the reason for unlocking."
+                        } else if model.is_locked {
+                            "The session is locked. Enter your password to unlock it."
+                        } else if model.has_password {
+                            "Lock the session to prevent timers from being loosened."
+                        } else {
+                            "Set a password below before locking the session."
+                        },
+                        add_css_class: "dim-label",
+                    },
+                },
+
+                // Unlock Entry Boxes:
+                gtk::ListBox {
+                    set_selection_mode: gtk::SelectionMode::None,
+                    add_css_class: "boxed-list",
+                    #[watch]
+                    set_visible: model.is_locked,
+
+                    #[name = "unlock_password"]
+                    adw::PasswordEntryRow {
+                        #[watch]
+                        set_title: if model.has_partner_mode { "Password or Unlock Code" } else { "Password" },
+                    },
+
+                    #[name = "unlock_reason"]
+                    adw::EntryRow {
+                        set_title: "Reason",
+                    },
+                },
+
+                // Lock / Unlock Button:
+                gtk::Button {
+                    add_css_class: "pill",
+                    set_hexpand: true,
+                    set_halign: gtk::Align::Fill,
+                    #[watch]
+                    set_sensitive: model.has_password,
+
+                    #[name = "lock_btn_content"]
+                    adw::ButtonContent {
+                        #[watch]
+                        set_label: if model.is_locked { "Unlock" } else { "Lock" },
+                        #[watch]
+                        set_icon_name: if model.is_locked {
+                            "changes-allow-symbolic"
+                        } else {
+                            "changes-prevent-symbolic"
+                        },
+                    },
+                    set_child: Some(&lock_btn_content),
+
+                    connect_clicked[sender] => move |_| {
+                        sender.input(SettingsInput::ToggleLock);
+                    },
+                },
+
+                // Pending Unlock:
+                adw::ActionRow {
+                    add_css_class: "card",
+                    #[watch]
+                    set_visible: model.unlock_at.is_some(),
+                    set_title: "Unlock requested",
+                    #[watch]
+                    set_subtitle: &model.unlock_at
+                        .map(|t| format!("Unlocks at {}", t.with_timezone(&Local).format("%H:%M")))
+                        .unwrap_or_default(),
+
+                    add_suffix = &gtk::Button {
+                        set_label: "Cancel",
+                        set_valign: gtk::Align::Center,
+                        add_css_class: "flat",
+
+                        connect_clicked[sender] => move |_| {
+                            sender.input(SettingsInput::CancelUnlock);
+                        },
+                    },
+                },
+
+                // Pending Cooling-Off Period:
+                adw::ActionRow {
+                    add_css_class: "card",
+                    #[watch]
+                    set_visible: model.cooling_off_at.is_some(),
+                    set_title: "Shorter cooling-off period requested",
+                    #[watch]
+                    set_subtitle: &model.cooling_off_at
+                        .map(|(minutes, t)| format!("{} minutes from {}", minutes, t.with_timezone(&Local).format("%H:%M")))
+                        .unwrap_or_default(),
+                },
+
+                // Pending Day Start:
+                adw::ActionRow {
+                    add_css_class: "card",
+                    #[watch]
+                    set_visible: model.day_start_at.is_some(),
+                    set_title: "New day start requested",
+                    #[watch]
+                    set_subtitle: &model.day_start_at
+                        .map(|(hour, t)| format!("{}:00 from {}", hour, t.with_timezone(&Local).format("%H:%M")))
+                        .unwrap_or_default(),
+                },
 
+                // Pending Timer Changes:
                 gtk::Label {
                     set_xalign: 0.0,
-                    set_label: "Session Lock",
+                    set_margin_top: 12,
+                    set_label: "Pending Changes",
                     add_css_class: "title-2",
+                    #[watch]
+                    set_visible: !model.pending_factory.is_empty(),
                 },
 
+                #[local_ref]
+                pending_list -> gtk::ListBox {
+                    set_selection_mode: gtk::SelectionMode::None,
+                    add_css_class: "boxed-list",
+                    #[watch]
+                    set_visible: !model.pending_factory.is_empty(),
+                },
+
+                // Password Header:
                 gtk::Label {
                     set_xalign: 0.0,
-                    set_wrap: true,
-                    set_label: "Enter your password to lock or unlock the session.",
-                    add_css_class: "dim-label",
+                    set_margin_top: 12,
+                    set_label: "Password",
+                    add_css_class: "title-2",
                 },
-            },
 
-            // Password Entry Box:
-            #[name = "password"]
-            adw::PasswordEntryRow {
-                set_title: "Password",
-            },
+                // Password Entry Boxes:
+                gtk::ListBox {
+                    set_selection_mode: gtk::SelectionMode::None,
+                    add_css_class: "boxed-list",
 
-            // Submit Button:
-            gtk::Button {
-                add_css_class: "pill",
-                set_hexpand: true,
-                set_halign: gtk::Align::Fill,
+                    #[name = "current_password"]
+                    adw::PasswordEntryRow {
+                        set_title: "Current Password",
+                        #[watch]
+                        set_visible: model.has_password,
+                    },
 
-                #[name = "lock_btn_content"]
-                adw::ButtonContent {
-                    #[watch]
-                    set_label: if model.is_locked { "Unlock" } else { "Lock" },
-                    #[watch]
-                    set_icon_name: if model.is_locked {
-                        "changes-allow-symbolic"
-                    } else {
-                        "changes-prevent-symbolic"
+                    #[name = "new_password"]
+                    adw::PasswordEntryRow {
+                        set_title: "New Password",
+                    },
+
+                    #[name = "confirm_password"]
+                    adw::PasswordEntryRow {
+                        set_title: "Confirm Password",
                     },
                 },
-                set_child: Some(&lock_btn_content),
 
-                connect_clicked[sender] => move |_| {
-                    sender.input(SettingsInput::SubmitPassword);
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 8,
+                    set_homogeneous: true,
+
+                    gtk::Button {
+                        add_css_class: "pill",
+                        #[watch]
+                        set_label: if model.has_password { "Change Password" } else { "Set Password" },
+
+                        connect_clicked[sender] => move |_| {
+                            sender.input(SettingsInput::ChangePassword);
+                        },
+                    },
+
+                    gtk::Button {
+                        add_css_class: "pill",
+                        add_css_class: "destructive-action",
+                        set_label: "Remove Password",
+                        #[watch]
+                        set_sensitive: model.has_password,
+
+                        connect_clicked[sender] => move |_| {
+                            sender.input(SettingsInput::ClearPassword);
+                        },
+                    },
                 },
-            },
+
+                // Configuration Header:
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 6,
+                    set_margin_top: 12,
+
+                    gtk::Label {
+                        set_xalign: 0.0,
+                        set_label: "Configuration",
+                        add_css_class: "title-2",
+                    },
+
+                    gtk::Label {
+                        set_xalign: 0.0,
+                        set_wrap: true,
+                        set_label: "The daemon's configuration file. The [clock], [storage] and [daemon] sections apply after a restart.",
+                        add_css_class: "dim-label",
+                    },
+                },
+
+                // Configuration Editor:
+                gtk::ScrolledWindow {
+                    set_min_content_height: 240,
+                    add_css_class: "card",
+
+                    gtk::TextView {
+                        set_monospace: true,
+                        set_top_margin: 8,
+                        set_bottom_margin: 8,
+                        set_left_margin: 8,
+                        set_right_margin: 8,
+                        set_buffer: Some(&model.config_buffer),
+                    },
+                },
+
+                gtk::Box {
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_spacing: 8,
+                    set_homogeneous: true,
+
+                    gtk::Button {
+                        add_css_class: "pill",
+                        set_label: "Reload File",
+
+                        connect_clicked[sender] => move |_| {
+                            sender.input(SettingsInput::ReloadConfig);
+                        },
+                    },
+
+                    gtk::Button {
+                        add_css_class: "pill",
+                        add_css_class: "suggested-action",
+                        set_label: "Apply",
+
+                        connect_clicked[sender] => move |_| {
+                            sender.input(SettingsInput::ApplyConfig);
+                        },
+                    },
+                },
+
+                // Rejection Message:
+                gtk::Label {
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    add_css_class: "error",
+                    #[watch]
+                    set_visible: model.error.is_some(),
+                    #[watch]
+                    set_label: model.error.as_deref().unwrap_or(""),
+                },
+            }
         }
     }
 
@@ -110,14 +373,30 @@ impl Component for SettingsPage {
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
+        let pending_factory = FactoryVecDeque::<PendingChangeModel>::builder()
+            .launch(gtk::ListBox::new())
+            .forward(sender.input_sender(), |o| match o {
+                PendingChangeOutput::Cancel(id) => SettingsInput::CancelTimerChange(id),
+            });
+
         let model = SettingsPage {
             dbus_conn: params.dbus_conn,
             is_locked: false,
+            has_password: false,
+            has_partner_mode: false,
+            unlock_at: None,
+            cooling_off_at: None,
+            day_start_at: None,
+            pending_factory,
+            config_buffer: gtk::TextBuffer::new(None),
+            error: None,
         };
+        let pending_list = model.pending_factory.widget();
         let widgets = view_output!();
 
         sender.input(SettingsInput::LoadLockStatus);
-        
+        sender.input(SettingsInput::LoadConfig);
+
         ComponentParts { model, widgets }
     }
 
@@ -130,37 +409,121 @@ impl Component for SettingsPage {
             _root: &Self::Root,
     ) {
         match message {
-            SettingsInput::SubmitPassword => {
+            SettingsInput::ToggleLock => {
                 let dbus_conn = self.dbus_conn.clone();
-                let password = widgets
-                    .password
-                    .text()
-                    .to_string();
-                widgets.password.set_text("");
-                
+                let is_locked = self.is_locked;
+                let credential = widgets.unlock_password.text().to_string();
+                let reason = widgets.unlock_reason.text().to_string();
+                widgets.unlock_password.set_text("");
+
                 sender.spawn_oneshot_command(move || {
-                    let proxy = DaemonContextProxyBlocking::builder(&dbus_conn)
-                        .destination(DBus::host_name(&Host::UserDaemon)).unwrap()
-                        .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext)).unwrap()
-                        .build().unwrap();
+                    let proxy = build_proxy(&dbus_conn);
+                    // The unlock may be queued for the cooling-off period, which the reload shows.
+                    let result = if is_locked {
+                        proxy.unlock(credential, reason).map(|_| ())
+                    } else {
+                        proxy.lock()
+                    };
 
-                    let do_unlock = proxy.process_password_submission(password).unwrap();
-                    SettingsCmd::LockStatusLoaded(!do_unlock)
+                    match result {
+                        Ok(()) => load_lock_status(&proxy),
+                        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+                    }
+                });
+            },
+            SettingsInput::ChangePassword => {
+                let old_password = widgets.current_password.text().to_string();
+                let new_password = widgets.new_password.text().to_string();
+                let confirmation = widgets.confirm_password.text().to_string();
+                widgets.current_password.set_text("");
+                widgets.new_password.set_text("");
+                widgets.confirm_password.set_text("");
+
+                if new_password != confirmation {
+                    self.error = Some("The passwords do not match.".to_string());
+                    self.update_view(widgets, sender);
+                    return;
+                }
+
+                let dbus_conn = self.dbus_conn.clone();
+                sender.spawn_oneshot_command(move || {
+                    let proxy = build_proxy(&dbus_conn);
+                    match proxy.change_password(old_password, new_password) {
+                        Ok(()) => load_lock_status(&proxy),
+                        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+                    }
+                });
+            },
+            SettingsInput::ClearPassword => {
+                let dbus_conn = self.dbus_conn.clone();
+                let password = widgets.current_password.text().to_string();
+                widgets.current_password.set_text("");
+
+                sender.spawn_oneshot_command(move || {
+                    let proxy = build_proxy(&dbus_conn);
+                    match proxy.clear_password(password) {
+                        Ok(()) => load_lock_status(&proxy),
+                        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+                    }
+                });
+            },
+            SettingsInput::CancelUnlock => {
+                let dbus_conn = self.dbus_conn.clone();
+                sender.spawn_oneshot_command(move || {
+                    let proxy = build_proxy(&dbus_conn);
+                    match proxy.cancel_unlock() {
+                        Ok(()) => load_lock_status(&proxy),
+                        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+                    }
+                });
+            },
+            SettingsInput::CancelTimerChange(id) => {
+                let dbus_conn = self.dbus_conn.clone();
+                sender.spawn_oneshot_command(move || {
+                    let proxy = build_proxy(&dbus_conn);
+                    match proxy.cancel_timer_change(id) {
+                        Ok(()) => load_lock_status(&proxy),
+                        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+                    }
                 });
             },
             SettingsInput::LoadLockStatus => {
                 let dbus_conn = self.dbus_conn.clone();
                 sender.spawn_oneshot_command(move || {
-                    let proxy = DaemonContextProxyBlocking::builder(&dbus_conn)
-                        .destination(DBus::host_name(&Host::UserDaemon)).unwrap()
-                        .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext)).unwrap()
-                        .build().unwrap();
+                    load_lock_status(&build_proxy(&dbus_conn))
+                });
+            },
+            SettingsInput::LoadConfig => {
+                let dbus_conn = self.dbus_conn.clone();
+                sender.spawn_oneshot_command(move || {
+                    load_config(&build_proxy(&dbus_conn))
+                });
+            },
+            SettingsInput::ReloadConfig => {
+                let dbus_conn = self.dbus_conn.clone();
+                sender.spawn_oneshot_command(move || {
+                    let proxy = build_proxy(&dbus_conn);
+                    match proxy.reload_config() {
+                        Ok(()) => load_config(&proxy),
+                        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+                    }
+                });
+            },
+            SettingsInput::ApplyConfig => {
+                let dbus_conn = self.dbus_conn.clone();
+                let (start, end) = self.config_buffer.bounds();
+                let contents = self.config_buffer.text(&start, &end, false).to_string();
 
-                    let is_locked = proxy.is_locked().unwrap();
-                    SettingsCmd::LockStatusLoaded(is_locked)
+                sender.spawn_oneshot_command(move || {
+                    let proxy = build_proxy(&dbus_conn);
+                    // The daemon validates the contents and names every problem.
+                    match proxy.update_config(contents) {
+                        Ok(()) => load_config(&proxy),
+                        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+                    }
                 });
             }
-        }    
+        }
     }
 
     fn update_cmd(
@@ -170,10 +533,68 @@ impl Component for SettingsPage {
         _root: &Self::Root,
     ) {
         match message {
-            SettingsCmd::LockStatusLoaded(is_locked) => {
+            SettingsCmd::LockStatusLoaded { is_locked, has_password, has_partner_mode, pending } => {
                 self.is_locked = is_locked;
+                self.has_password = has_password;
+                self.has_partner_mode = has_partner_mode;
+                self.unlock_at = pending.unlock_pending.then_some(pending.unlock_at);
+                self.cooling_off_at = pending.cooling_off_pending
+                    .then_some((pending.cooling_off_minutes, pending.cooling_off_at));
+                self.day_start_at = pending.day_start_pending
+                    .then_some((pending.day_start_hour, pending.day_start_at));
+
+                let mut guard = self.pending_factory.guard();
+                guard.clear();
+                for change in pending.timer_changes {
+                    guard.push_back(change);
+                }
+                drop(guard);
+
+                self.error = None;
                 sender.output(SettingsOut::LockStatusChange(is_locked)).unwrap();
+            },
+            SettingsCmd::ConfigLoaded(contents) => {
+                self.config_buffer.set_text(&contents);
+                self.error = None;
+            },
+            SettingsCmd::RequestRejected(msg) => {
+                self.error = Some(msg);
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// A proxy of the daemon context on the connection of the GUI.
+pub fn build_proxy(dbus_conn: &BlockingConnection) -> DaemonContextProxyBlocking<'static> {
+    DaemonContextProxyBlocking::builder(dbus_conn)
+        .destination(DBus::host_name(&Host::UserDaemon)).unwrap()
+        .path(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext)).unwrap()
+        .build().unwrap()
+}
+
+fn load_lock_status(proxy: &DaemonContextProxyBlocking) -> SettingsCmd {
+    SettingsCmd::LockStatusLoaded {
+        is_locked: proxy.is_locked().unwrap(),
+        has_password: proxy.has_password().unwrap(),
+        has_partner_mode: proxy.has_partner_mode().unwrap(),
+        pending: proxy.get_pending_changes().unwrap(),
+    }
+}
+
+fn load_config(proxy: &DaemonContextProxyBlocking) -> SettingsCmd {
+    match proxy.get_config() {
+        Ok(contents) => SettingsCmd::ConfigLoaded(contents),
+        Err(e) => SettingsCmd::RequestRejected(error_message(e)),
+    }
+}
+
+/// Strip the D-Bus error name so that only the daemon's explanation is shown.
+pub fn error_message(e: fdo::Error) -> String {
+    match e {
+        fdo::Error::AccessDenied(msg)
+        | fdo::Error::LimitsExceeded(msg)
+        | fdo::Error::InvalidArgs(msg)
+        | fdo::Error::Failed(msg) => msg,
+        e => e.to_string(),
+    }
+}
@@ -0,0 +1,222 @@
+use relm4::{Component, ComponentSender, ComponentParts};
+use relm4::gtk;
+use relm4::gtk::prelude::*;
+use relm4::prelude::*;
+use chrono::{Days, Local, Utc};
+use zbus::blocking::Connection as BlockingConnection;
+
+use shared::types::daemon::{Coverage, WatcherStatus};
+use shared::types::day;
+use crate::components::coverage_gap::CoverageGapModel;
+use crate::pages::settings::{build_proxy, error_message};
+
+pub struct StatusPage {
+    dbus_conn: BlockingConnection,
+    /// The seconds of the day so far, and how many of them were tracked.
+    observed: u32,
+    covered: u32,
+    watchers: Vec<WatcherStatus>,
+    gap_factory: FactoryVecDeque<CoverageGapModel>,
+    /// The reason the coverage could not be loaded, if any.
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum StatusInput {
+    /// Query the daemon for the coverage of the current day.
+    LoadCoverage,
+}
+
+#[derive(Debug)]
+pub enum StatusCmd {
+    CoverageLoaded(Coverage),
+    RequestFailed(String),
+}
+
+#[derive(Debug)]
+pub struct StatusInit {
+    pub dbus_conn: BlockingConnection,
+}
+
+#[relm4::component(pub)]
+impl Component for StatusPage {
+    type Init = StatusInit;
+    type Input = StatusInput;
+    type CommandOutput = StatusCmd;
+    type Output = ();
+
+    view! {
+        #[root]
+        gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 14,
+            set_margin_top: 18,
+            set_margin_bottom: 18,
+            set_margin_start: 18,
+            set_margin_end: 18,
+
+            // Header:
+            gtk::Box {
+                set_orientation: gtk::Orientation::Vertical,
+                set_spacing: 6,
+
+                gtk::Label {
+                    set_xalign: 0.0,
+                    set_label: "Today's Coverage",
+                    add_css_class: "title-2",
+                },
+
+                gtk::Label {
+                    set_xalign: 0.0,
+                    set_wrap: true,
+                    add_css_class: "dim-label",
+                    #[watch]
+                    set_label: &format!(
+                        "Tracked {} of {} today.",
+                        fmt_hh_mm(model.covered),
+                        fmt_hh_mm(model.observed),
+                    ),
+                },
+            },
+
+            gtk::ProgressBar {
+                #[watch]
+                set_fraction: if model.observed == 0 {
+                    1.0
+                } else {
+                    model.covered as f64 / model.observed as f64
+                },
+            },
+
+            // Watchers:
+            gtk::Label {
+                set_xalign: 0.0,
+                set_wrap: true,
+                #[watch]
+                set_label: &fmt_watchers(&model.watchers),
+            },
+
+            // Gaps:
+            gtk::Label {
+                set_xalign: 0.0,
+                set_margin_top: 12,
+                set_label: "Gaps",
+                add_css_class: "title-2",
+                #[watch]
+                set_visible: !model.gap_factory.is_empty(),
+            },
+
+            gtk::ScrolledWindow {
+                set_vexpand: true,
+                #[watch]
+                set_visible: !model.gap_factory.is_empty(),
+
+                #[local_ref]
+                gap_list -> gtk::ListBox {
+                    set_selection_mode: gtk::SelectionMode::None,
+                    add_css_class: "boxed-list",
+                },
+            },
+
+            // Failure Message:
+            gtk::Label {
+                set_xalign: 0.0,
+                set_wrap: true,
+                add_css_class: "error",
+                #[watch]
+                set_visible: model.error.is_some(),
+                #[watch]
+                set_label: model.error.as_deref().unwrap_or(""),
+            },
+        }
+    }
+
+    fn init(
+        params: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let gap_factory = FactoryVecDeque::<CoverageGapModel>::builder()
+            .launch(gtk::ListBox::new())
+            .detach();
+
+        let model = StatusPage {
+            dbus_conn: params.dbus_conn,
+            observed: 0,
+            covered: 0,
+            watchers: Vec::new(),
+            gap_factory,
+            error: None,
+        };
+        let gap_list = model.gap_factory.widget();
+        let widgets = view_output!();
+
+        sender.input(StatusInput::LoadCoverage);
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, _root: &Self::Root) {
+        match message {
+            StatusInput::LoadCoverage => {
+                let dbus_conn = self.dbus_conn.clone();
+                sender.spawn_oneshot_command(move || {
+                    let proxy = build_proxy(&dbus_conn);
+                    let day_start_hour = proxy.day_start_hour().unwrap_or(0);
+                    let today = day::tracking_day(Utc::now(), day_start_hour);
+                    let start = day::day_start(today, day_start_hour);
+                    let end = day::day_start(today + Days::new(1), day_start_hour);
+                    match proxy.get_coverage(start, end) {
+                        Ok(coverage) => StatusCmd::CoverageLoaded(coverage),
+                        Err(e) => StatusCmd::RequestFailed(error_message(e)),
+                    }
+                });
+            },
+        }
+    }
+
+    fn update_cmd(
+        &mut self,
+        message: Self::CommandOutput,
+        _sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            StatusCmd::CoverageLoaded(coverage) => {
+                self.observed = (coverage.end.min(Utc::now()) - coverage.start).num_seconds().max(0) as u32;
+                self.covered = self.observed.saturating_sub(coverage.uncovered);
+                self.watchers = coverage.watchers;
+                self.error = None;
+
+                let mut guard = self.gap_factory.guard();
+                guard.clear();
+                for gap in coverage.gaps {
+                    guard.push_back(gap);
+                }
+            },
+            StatusCmd::RequestFailed(msg) => {
+                self.error = Some(msg);
+            },
+        }
+    }
+}
+
+fn fmt_watchers(watchers: &[WatcherStatus]) -> String {
+    if watchers.is_empty() {
+        return "No watcher has connected since the daemon started.".to_string();
+    }
+
+    watchers.iter()
+        .map(|w| format!(
+            "{}: {} (last seen {})",
+            w.host,
+            if w.connected { "connected" } else { "disconnected" },
+            w.last_seen.with_timezone(&Local).format("%H:%M"),
+        ))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn fmt_hh_mm(secs: u32) -> String {
+    format!("{}h {:02}m", secs / 3600, (secs / 60) % 60)
+}
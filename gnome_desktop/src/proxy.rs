@@ -1,16 +1,39 @@
+use chrono::{DateTime, Utc};
 use zbus::{fdo, proxy};
 use shared::types::schema::{FocusChange, Timer};
-use shared::types::daemon::DaemonSnapshot;
+use shared::types::daemon::{ActiveSession, Coverage, DaemonSnapshot, PendingChanges};
 
 #[proxy(interface = "com.activity_warden.DaemonContext")]
 pub trait DaemonContext {
     #[zbus(signal)]
     async fn duration_changed(&self, change: FocusChange) -> Result<()>;
+    #[zbus(signal)]
+    async fn lock_state_changed(&self, is_locked: bool) -> Result<()>;
+    #[zbus(signal)]
+    async fn pending_changes_changed(&self) -> Result<()>;
+    #[zbus(signal)]
+    async fn config_changed(&self) -> Result<()>;
+    #[zbus(property)]
+    fn active_sessions(&self) -> fdo::Result<Vec<ActiveSession>>;
+    #[zbus(property)]
+    fn day_start_hour(&self) -> fdo::Result<u32>;
 
     fn get_daemon_snapshot(&self) -> fdo::Result<DaemonSnapshot>;
+    fn get_coverage(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<Coverage>;
     fn insert_timer(&self, timer: Timer) -> fdo::Result<()>;
-    fn delete_timer(&self, timer: Timer) -> fdo::Result<()>;
-    fn update_timer(&self, timer: Timer) -> fdo::Result<()>;
+    fn delete_timer(&self, timer: Timer) -> fdo::Result<u32>;
+    fn update_timer(&self, timer: Timer) -> fdo::Result<u32>;
     fn is_locked(&self) -> fdo::Result<bool>;
-    fn process_password_submission(&self, password: String) -> fdo::Result<bool>;
+    fn has_password(&self) -> fdo::Result<bool>;
+    fn lock(&self) -> fdo::Result<()>;
+    fn unlock(&self, credential: String, reason: String) -> fdo::Result<u32>;
+    fn change_password(&self, old_password: String, new_password: String) -> fdo::Result<()>;
+    fn clear_password(&self, password: String) -> fdo::Result<()>;
+    fn has_partner_mode(&self) -> fdo::Result<bool>;
+    fn get_pending_changes(&self) -> fdo::Result<PendingChanges>;
+    fn cancel_unlock(&self) -> fdo::Result<()>;
+    fn cancel_timer_change(&self, id: i64) -> fdo::Result<()>;
+    fn get_config(&self) -> fdo::Result<String>;
+    fn reload_config(&self) -> fdo::Result<()>;
+    fn update_config(&self, contents: String) -> fdo::Result<()>;
 }
\ No newline at end of file
@@ -1,8 +1,8 @@
 use serde::{Serialize, Deserialize};
-use zvariant::Type;
+use zvariant::{OwnedValue, Type, Value};
 use std::{fmt, str};
 
-#[derive(Serialize, Deserialize, Type, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[derive(Serialize, Deserialize, Type, Value, OwnedValue, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub enum Host {
     /// The main decision-maker using the data from the watchers.
     UserDaemon,
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str};
+use zvariant::Type;
+
+use crate::types::schema::{DailySummary, FocusChange, Timer};
+
+/// The version of the JSON backup document written by this build.
+/// Documents with a newer version are rejected on import.
+pub const BACKUP_VERSION: u32 = 2;
+
+/// A complete snapshot of the data owned by the `User Daemon`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    /// The version of the document layout.
+    pub version: u32,
+    /// The moment at which the backup was taken.
+    pub exported_at: DateTime<Utc>,
+    pub timers: Vec<Timer>,
+    pub focus_changes: Vec<FocusChange>,
+    /// The days that were rolled up, whose sessions are no longer among `focus_changes`.
+    /// Backups of version 1 do not contain any.
+    #[serde(default)]
+    pub daily_summaries: Vec<DailySummary>,
+    #[serde(default)]
+    pub settings: BackupSettings,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct BackupSettings {
+    /// The hash of the lock password, which only backups of earlier versions contain.
+    /// It is restored if no password is configured.
+    #[serde(default)]
+    pub password_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Type, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackupFormat {
+    /// The full `Backup` document.
+    Json,
+    /// Only the focus changes, one per row.
+    Csv,
+}
+
+impl fmt::Display for BackupFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let enum_str = match self {
+            BackupFormat::Json => "json",
+            BackupFormat::Csv => "csv",
+        };
+
+        f.write_str(enum_str)
+    }
+}
+
+impl str::FromStr for BackupFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(BackupFormat::Json),
+            "csv" => Ok(BackupFormat::Csv),
+            _ => Err(format!("'{}' is not a valid backup format", s)),
+        }
+    }
+}
+
+/// The number of records that were newly written by an import.
+/// Records that already existed are counted as skipped.
+#[derive(Serialize, Deserialize, Type, Clone, Debug, Default)]
+pub struct ImportSummary {
+    pub timers_imported: u32,
+    pub timers_skipped: u32,
+    pub focus_changes_imported: u32,
+    pub focus_changes_skipped: u32,
+    pub daily_summaries_imported: u32,
+    pub daily_summaries_skipped: u32,
+    /// Whether the password hash from the backup was applied.
+    pub password_imported: bool,
+}
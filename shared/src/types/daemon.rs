@@ -1,9 +1,11 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use zvariant::Type;
+use zvariant::{OwnedValue, StructureBuilder, Type, Value};
 
-use crate::types::schema::Timer;
+use crate::types::schema::{FocusChange, Timer};
 use crate::dbus::Host;
 use std::collections::HashMap;
+use std::{fmt, str};
 
 type DisplayName = String;
 pub type DurationMap = HashMap<Host, HashMap<DisplayName, u32>>;
@@ -13,3 +15,269 @@ pub struct DaemonSnapshot {
     pub timers: Vec<Timer>,
     pub durations: DurationMap,
 }
+
+/// A single page of the raw session timeline.
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct SessionPage {
+    /// The sessions on this page, ordered by their timestamp.
+    pub sessions: Vec<FocusChange>,
+    /// The total number of sessions within the requested range.
+    pub total: u32,
+}
+
+/// A session that is currently in progress on one of the watchers.
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct ActiveSession {
+    pub host: Host,
+    pub display_name: String,
+    /// The moment at which the session started.
+    pub started_at: DateTime<Utc>,
+    /// The seconds spent on the display name today, including this session.
+    pub duration: u32,
+    /// Whether a timer applies to the display name today.
+    pub has_timer: bool,
+    /// The seconds remaining until the display name is blocked.
+    /// This is only meaningful if `has_timer` is set.
+    pub remaining: u32,
+    /// Whether the session currently accrues time, which an aggregation policy may pause.
+    pub counting: bool,
+}
+
+// `ActiveSession` is published as a D-Bus property, which requires conversions to
+// and from `Value`. They follow the signature derived by `Type`, with the start time
+// as a string.
+impl From<ActiveSession> for Value<'static> {
+    fn from(session: ActiveSession) -> Self {
+        StructureBuilder::new()
+            .add_field(session.host)
+            .add_field(session.display_name)
+            .add_field(session.started_at.to_rfc3339())
+            .add_field(session.duration)
+            .add_field(session.has_timer)
+            .add_field(session.remaining)
+            .add_field(session.counting)
+            .build()
+            .expect("An active session always has fields.")
+            .into()
+    }
+}
+
+impl TryFrom<Value<'_>> for ActiveSession {
+    type Error = zvariant::Error;
+
+    fn try_from(value: Value<'_>) -> Result<Self, Self::Error> {
+        let Value::Structure(structure) = value else {
+            return Err(zvariant::Error::IncorrectType);
+        };
+        let [host, display_name, started_at, duration, has_timer, remaining, counting]: [Value<'_>; 7] = structure
+            .into_fields()
+            .try_into()
+            .map_err(|_| zvariant::Error::IncorrectType)?;
+
+        let started_at: String = started_at.try_into()?;
+        Ok(ActiveSession {
+            host: host.try_into()?,
+            display_name: display_name.try_into()?,
+            started_at: DateTime::parse_from_rfc3339(&started_at)
+                .map_err(|e| zvariant::Error::Message(e.to_string()))?
+                .with_timezone(&Utc),
+            duration: duration.try_into()?,
+            has_timer: has_timer.try_into()?,
+            remaining: remaining.try_into()?,
+            counting: counting.try_into()?,
+        })
+    }
+}
+
+impl TryFrom<OwnedValue> for ActiveSession {
+    type Error = zvariant::Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        Value::from(value).try_into()
+    }
+}
+
+/// The credential that was used to unlock the daemon.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnlockMethod {
+    /// The password configured on the daemon.
+    Password,
+    /// A time-based code generated from the secret shared with a partner.
+    PartnerCode,
+}
+
+impl fmt::Display for UnlockMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let to_string = match self {
+            UnlockMethod::Password => "password",
+            UnlockMethod::PartnerCode => "partner_code",
+        };
+
+        f.write_str(to_string)
+    }
+}
+
+impl str::FromStr for UnlockMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "password" => Ok(UnlockMethod::Password),
+            "partner_code" => Ok(UnlockMethod::PartnerCode),
+            _ => Err(format!("Unknown unlock method: {}", s)),
+        }
+    }
+}
+
+/// A record of the daemon being unlocked, kept for review by a partner.
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct UnlockEvent {
+    pub timestamp: DateTime<Utc>,
+    pub method: UnlockMethod,
+    /// The explanation given by the user when unlocking.
+    pub reason: String,
+}
+
+/// The modification of a timer that is waiting out the cooling-off period.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimerAction {
+    Update,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct PendingTimerChange {
+    /// The identifier used to cancel the change.
+    pub id: i64,
+    pub action: TimerAction,
+    /// The timer as it will be stored once the change applies, or the timer to delete.
+    pub timer: Timer,
+    pub requested_at: DateTime<Utc>,
+    pub apply_at: DateTime<Utc>,
+}
+
+/// The changes that loosen the restrictions and therefore only take effect
+/// once the cooling-off period has elapsed.
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct PendingChanges {
+    /// Whether an unlock has been requested.
+    pub unlock_pending: bool,
+    /// The moment at which the unlock applies.
+    /// This is only meaningful if `unlock_pending` is set.
+    pub unlock_at: DateTime<Utc>,
+    /// The pending timer changes, ordered by the moment at which they apply.
+    pub timer_changes: Vec<PendingTimerChange>,
+    /// Whether a shorter cooling-off period has been requested.
+    pub cooling_off_pending: bool,
+    /// The requested cooling-off period in minutes.
+    /// This is only meaningful if `cooling_off_pending` is set.
+    pub cooling_off_minutes: u32,
+    /// The moment at which the shorter cooling-off period applies.
+    /// This is only meaningful if `cooling_off_pending` is set.
+    pub cooling_off_at: DateTime<Utc>,
+    /// Whether a new day start has been requested.
+    pub day_start_pending: bool,
+    /// The requested hour at which the day starts.
+    /// This is only meaningful if `day_start_pending` is set.
+    pub day_start_hour: u32,
+    /// The moment at which the new day start applies.
+    /// This is only meaningful if `day_start_pending` is set.
+    pub day_start_at: DateTime<Utc>,
+}
+
+/// The reason why activity may have gone untracked.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapKind {
+    /// The daemon was not running.
+    DaemonDown,
+    /// A watcher stopped sending heartbeats without disconnecting cleanly.
+    WatcherDisconnected,
+    /// The wall clock moved independently of the elapsed time.
+    ClockJump,
+}
+
+impl fmt::Display for GapKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let to_string = match self {
+            GapKind::DaemonDown => "daemon_down",
+            GapKind::WatcherDisconnected => "watcher_disconnected",
+            GapKind::ClockJump => "clock_jump",
+        };
+
+        f.write_str(to_string)
+    }
+}
+
+impl str::FromStr for GapKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "daemon_down" => Ok(GapKind::DaemonDown),
+            "watcher_disconnected" => Ok(GapKind::WatcherDisconnected),
+            "clock_jump" => Ok(GapKind::ClockJump),
+            _ => Err(format!("Unknown gap kind: {}", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct CoverageGap {
+    pub kind: GapKind,
+    /// The watcher that disconnected, or the daemon itself for other kinds.
+    pub host: Host,
+    pub start: DateTime<Utc>,
+    /// The end of the gap. A gap that is still open ends at the time of the query.
+    pub end: DateTime<Utc>,
+    /// Whether the gap is still open.
+    pub ongoing: bool,
+    pub detail: String,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct WatcherStatus {
+    pub host: Host,
+    pub connected: bool,
+    /// The moment of the last event or heartbeat from the watcher.
+    pub last_seen: DateTime<Utc>,
+}
+
+/// How much of a time range was observed by the daemon and its watchers.
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct Coverage {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// The seconds within the range that fall into at least one gap.
+    pub uncovered: u32,
+    /// The gaps overlapping the range, ordered by their start.
+    pub gaps: Vec<CoverageGap>,
+    /// The watchers that have been seen since the daemon started.
+    pub watchers: Vec<WatcherStatus>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_session_round_trips_through_a_value() {
+        let session = ActiveSession {
+            host: Host::FirefoxWatcher,
+            display_name: "example.com".to_string(),
+            started_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            duration: 120,
+            has_timer: true,
+            remaining: 60,
+            counting: false,
+        };
+
+        let value = Value::from(session.clone());
+        assert_eq!(value.value_signature(), ActiveSession::SIGNATURE);
+
+        let restored = ActiveSession::try_from(value).unwrap();
+        assert_eq!(restored.host, session.host);
+        assert_eq!(restored.display_name, session.display_name);
+        assert_eq!(restored.started_at, session.started_at);
+        assert_eq!((restored.duration, restored.has_timer, restored.remaining, restored.counting), (120, true, 60, false));
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime, Utc};
+
+/// The moment at which the tracking day `date` starts, `hour` hours after local midnight.
+/// If that hour does not exist on `date`, i.e. when the clocks move forward, the day
+/// starts the same number of hours after midnight instead.
+pub fn day_start(date: NaiveDate, hour: u32) -> DateTime<Utc> {
+    let start = NaiveTime::from_hms_opt(hour, 0, 0).expect("The day starts within 24 hours.");
+    if let Some(start) = date.and_time(start).and_local_timezone(Local).earliest() {
+        return start.with_timezone(&Utc);
+    }
+
+    let midnight = date.and_time(NaiveTime::MIN)
+        .and_local_timezone(Local)
+        .earliest()
+        .expect("Failed to find a Local midnight.");
+    midnight.with_timezone(&Utc) + chrono::Duration::hours(hour as i64)
+}
+
+/// The tracking day that contains `now`. Before `hour`, the time still belongs to the previous day.
+pub fn tracking_day(now: DateTime<Utc>, hour: u32) -> NaiveDate {
+    let date = now.with_timezone(&Local).date_naive();
+    if now < day_start(date, hour) {
+        date - Days::new(1)
+    } else {
+        date
+    }
+}
+
+/// The moment at which the tracking day after the one that contains `now` starts.
+pub fn next_day_start(now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    day_start(tracking_day(now, hour) + Days::new(1), hour)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn local(day: u32, hour: u32) -> DateTime<Utc> {
+        Local.with_ymd_and_hms(2025, 3, day, hour, 0, 0).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn the_night_belongs_to_the_previous_day() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 14).unwrap();
+        assert_eq!(tracking_day(local(15, 1), 4), date);
+        assert_eq!(tracking_day(local(15, 4), 4), date + Days::new(1));
+        assert_eq!(tracking_day(local(15, 1), 0), date + Days::new(1));
+    }
+
+    #[test]
+    fn the_next_day_starts_at_the_configured_hour() {
+        assert_eq!(next_day_start(local(15, 1), 4), local(15, 4));
+        assert_eq!(next_day_start(local(15, 4), 4), local(16, 4));
+        assert_eq!(next_day_start(local(14, 23), 0), local(15, 0));
+    }
+}
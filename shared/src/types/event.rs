@@ -3,11 +3,18 @@ use zvariant::Type;
 
 use crate::dbus::Host;
 
+/// The number of seconds between two heartbeats of a running watcher.
+pub const HEARTBEAT_INTERVAL_SECS: u64 = 30;
+
 #[derive(Serialize, Deserialize, Type, Clone, Debug)]
 pub enum EventType {
     FocusChange,
     FocusLost,
     AFK,
+    /// Sent periodically to signal that the watcher is still running.
+    Heartbeat,
+    /// Sent when the watcher stops cleanly, i.e. because the browser closed.
+    Disconnect,
 }
 
 #[derive(Serialize, Deserialize, Type, Clone, Debug)]
@@ -16,8 +23,56 @@ pub struct Event {
     pub source: Host,
     /// The name for which the event will be stored and displayed.
     pub display_name: String,
-    /// This field is optionally used to store additional information
-    /// for a given `EventType`. Algebraic types will not work due to
-    /// the `Type` macro constraint.
-    pub metadata: String,
+    /// What the watcher knows about the focus, beyond its display name.
+    pub metadata: EventMetadata,
+}
+
+/// The details of a focus that a watcher may report. Algebraic types,
+/// including `Option`, will not work due to the `Type` macro constraint,
+/// so an unknown ID is `NO_ID` and an unknown text is empty.
+/// Which fields a host may set is checked by the daemon as events arrive.
+#[derive(Serialize, Deserialize, Type, Clone, Debug, PartialEq, Eq)]
+pub struct EventMetadata {
+    /// The browser tab, which the Firefox watcher closes by its ID.
+    pub tab_id: i64,
+    /// The window of the browser or the desktop.
+    pub window_id: i64,
+    /// The address of the browser tab.
+    pub url: String,
+    /// The title of the tab or window.
+    pub title: String,
+    /// The ID of the desktop application, i.e. `org.gnome.Nautilus`.
+    pub app_id: String,
+}
+
+impl EventMetadata {
+    /// The value of an ID that the watcher does not know.
+    pub const NO_ID: i64 = -1;
+
+    /// The metadata of a tab of the browser.
+    pub fn tab(tab_id: i64, url: &str, title: &str) -> Self {
+        EventMetadata {
+            tab_id,
+            url: url.to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Whether nothing is set, as for the events that carry no focus.
+    pub fn is_empty(&self) -> bool {
+        *self == EventMetadata::default()
+    }
+}
+
+impl Default for EventMetadata {
+    fn default() -> Self {
+        EventMetadata {
+            tab_id: Self::NO_ID,
+            window_id: Self::NO_ID,
+            url: String::new(),
+            title: String::new(),
+            app_id: String::new(),
+        }
+    }
 }
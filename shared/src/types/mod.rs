@@ -1,7 +1,9 @@
 pub mod event;
 pub mod schema;
 pub mod daemon;
+pub mod backup;
+pub mod day;
 
-pub use event::{Event, EventType};
+pub use event::{Event, EventMetadata, EventType};
 
 pub struct Watcher;
\ No newline at end of file
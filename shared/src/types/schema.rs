@@ -1,4 +1,4 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Serialize, Deserialize};
 use std::fmt;
 use zvariant::Type;
@@ -31,6 +31,18 @@ pub struct FocusChange {
     pub duration: u32,
 }
 
+/// The total time spent on a display name during a tracking day, into
+/// which the raw sessions of that day are rolled up by the retention policy.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DailySummary {
+    pub host: Host,
+    pub display_name: String,
+    /// The tracking day on which the sessions ended.
+    pub day: NaiveDate,
+    /// The summed length of the sessions.
+    pub duration: u32,
+}
+
 pub type Password = String;
 
 #[derive(Type, Serialize, Deserialize)]
@@ -39,6 +51,16 @@ pub enum AWTables {
     Timers,
     /// Stores changes to the currently focused window.
     FocusChanges,
+    /// Stores the daily totals of focus changes past the retention window.
+    DailySummaries,
+    /// Stores every unlock of the daemon along with its reason.
+    UnlockEvents,
+    /// Stores the changes that wait out the cooling-off period.
+    PendingChanges,
+    /// Stores the periods in which activity may have gone untracked.
+    CoverageGaps,
+    /// Stores the last checkpoint of the sessions in progress.
+    ActiveSessions,
 }
 
 impl fmt::Display for AWTables {
@@ -46,6 +68,11 @@ impl fmt::Display for AWTables {
         let enum_str = match self {
             AWTables::FocusChanges => "focus_changes",
             AWTables::Timers => "timers",
+            AWTables::DailySummaries => "focus_changes_daily",
+            AWTables::UnlockEvents => "unlock_events",
+            AWTables::PendingChanges => "pending_changes",
+            AWTables::CoverageGaps => "coverage_gaps",
+            AWTables::ActiveSessions => "active_sessions",
         };
 
         f.write_str(enum_str)
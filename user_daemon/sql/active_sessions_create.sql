@@ -0,0 +1,6 @@
+CREATE TABLE active_sessions (
+    host VARCHAR(64) NOT NULL PRIMARY KEY,
+    display_name VARCHAR(64) NOT NULL,
+    ts TIMESTAMP(6) NOT NULL,
+    duration_seconds INT UNSIGNED NOT NULL
+);
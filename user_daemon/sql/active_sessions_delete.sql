@@ -0,0 +1,2 @@
+DELETE FROM active_sessions
+WHERE host = ?1;
@@ -0,0 +1 @@
+DELETE FROM active_sessions;
@@ -0,0 +1,2 @@
+INSERT INTO active_sessions (display_name, host, ts, duration_seconds)
+VALUES (?1, ?2, ?3, ?4);
@@ -0,0 +1,6 @@
+SELECT
+  display_name,
+  host,
+  ts,
+  duration_seconds
+FROM active_sessions;
@@ -0,0 +1,8 @@
+CREATE TABLE coverage_gaps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind VARCHAR(32) NOT NULL,
+    host VARCHAR(64) NOT NULL,
+    start_ts TIMESTAMP(6) NOT NULL,
+    end_ts TIMESTAMP(6),
+    detail TEXT NOT NULL
+);
\ No newline at end of file
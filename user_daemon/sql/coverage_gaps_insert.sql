@@ -0,0 +1,2 @@
+INSERT INTO coverage_gaps (kind, host, start_ts, end_ts, detail)
+VALUES (?1, ?2, ?3, ?4, ?5);
\ No newline at end of file
@@ -0,0 +1,10 @@
+SELECT
+  kind,
+  host,
+  start_ts,
+  end_ts,
+  detail
+FROM coverage_gaps
+WHERE start_ts < ?2
+  AND (end_ts IS NULL OR end_ts > ?1)
+ORDER BY start_ts, id;
\ No newline at end of file
@@ -0,0 +1,3 @@
+UPDATE coverage_gaps
+SET end_ts = ?2
+WHERE id = ?1;
\ No newline at end of file
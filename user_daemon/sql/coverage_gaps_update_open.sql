@@ -0,0 +1,3 @@
+UPDATE coverage_gaps
+SET end_ts = ?1
+WHERE end_ts IS NULL;
\ No newline at end of file
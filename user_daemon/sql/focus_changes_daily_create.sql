@@ -0,0 +1,7 @@
+CREATE TABLE focus_changes_daily (
+    display_name VARCHAR(64) NOT NULL,
+    host VARCHAR(64) NOT NULL,
+    day DATE NOT NULL,
+    duration_seconds INT UNSIGNED NOT NULL,
+    PRIMARY KEY (display_name, host, day)
+);
@@ -0,0 +1,4 @@
+INSERT INTO focus_changes_daily (display_name, host, day, duration_seconds)
+VALUES (?1, ?2, ?3, ?4)
+ON CONFLICT (display_name, host, day)
+DO UPDATE SET duration_seconds = duration_seconds + excluded.duration_seconds;
@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO focus_changes_daily (display_name, host, day, duration_seconds)
+VALUES (?1, ?2, ?3, ?4);
@@ -0,0 +1,7 @@
+SELECT
+  display_name,
+  host,
+  day,
+  duration_seconds
+FROM focus_changes_daily
+ORDER BY day, host, display_name;
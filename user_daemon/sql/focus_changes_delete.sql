@@ -0,0 +1,2 @@
+DELETE FROM focus_changes
+WHERE ts < ?1;
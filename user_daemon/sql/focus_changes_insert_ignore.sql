@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO focus_changes (display_name, host, ts, duration_seconds)
+VALUES (?1, ?2, ?3, ?4);
@@ -2,7 +2,15 @@ SELECT
   display_name,
   host,
   SUM(duration_seconds) AS total_duration
-FROM focus_changes
-WHERE ts >= ?1
-  AND ts < ?2
+FROM (
+  SELECT display_name, host, duration_seconds
+  FROM focus_changes
+  WHERE ts >= ?1
+    AND ts < ?2
+  UNION ALL
+  SELECT display_name, host, duration_seconds
+  FROM focus_changes_daily
+  WHERE day >= ?3
+    AND day < ?4
+)
 GROUP BY display_name, host;
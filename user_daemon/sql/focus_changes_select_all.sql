@@ -0,0 +1,7 @@
+SELECT
+  display_name,
+  host,
+  ts,
+  duration_seconds
+FROM focus_changes
+ORDER BY ts, host, display_name;
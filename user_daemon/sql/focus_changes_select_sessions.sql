@@ -0,0 +1,10 @@
+SELECT
+  display_name,
+  host,
+  ts,
+  duration_seconds
+FROM focus_changes
+WHERE ts >= ?1
+  AND ts < ?2
+ORDER BY ts, host, display_name
+LIMIT ?3 OFFSET ?4;
@@ -0,0 +1,4 @@
+SELECT COUNT(*)
+FROM focus_changes
+WHERE ts >= ?1
+  AND ts < ?2;
@@ -0,0 +1,6 @@
+CREATE TABLE pending_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    change TEXT NOT NULL,
+    requested_at TIMESTAMP(6) NOT NULL,
+    apply_at TIMESTAMP(6) NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO pending_changes (change, requested_at, apply_at)
+VALUES (?1, ?2, ?3);
\ No newline at end of file
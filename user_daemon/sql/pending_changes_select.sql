@@ -0,0 +1,7 @@
+SELECT
+  id,
+  change,
+  requested_at,
+  apply_at
+FROM pending_changes
+ORDER BY apply_at, id;
\ No newline at end of file
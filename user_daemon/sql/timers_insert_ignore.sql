@@ -0,0 +1,2 @@
+INSERT OR IGNORE INTO timers (display_name, host, time_limit, active_days)
+VALUES (?1, ?2, ?3, ?4);
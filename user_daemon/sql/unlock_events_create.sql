@@ -0,0 +1,5 @@
+CREATE TABLE unlock_events (
+    ts TIMESTAMP(6) NOT NULL,
+    method VARCHAR(16) NOT NULL,
+    reason TEXT NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+INSERT INTO unlock_events (ts, method, reason)
+VALUES (?1, ?2, ?3);
\ No newline at end of file
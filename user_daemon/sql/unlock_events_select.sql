@@ -0,0 +1,8 @@
+SELECT
+  ts,
+  method,
+  reason
+FROM unlock_events
+WHERE ts >= ?1
+  AND ts < ?2
+ORDER BY ts;
\ No newline at end of file
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use shared::dbus::Host;
+use crate::clock::Stamp;
+
+/// How the time is counted while several watchers report an active session at once,
+/// i.e. a browser tab along with the browser window reported by the desktop.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AggregationPolicy {
+    /// Count the session of every host.
+    #[default]
+    All,
+    /// Only count the session of the host that was focused last.
+    Foreground,
+    /// Only count the session of the host that describes the activity in the most detail.
+    MostSpecific,
+}
+
+/// How closely a host describes the activity of the user. A browser tab is
+/// more specific than the browser window that the desktop reports.
+pub fn specificity(host: &Host) -> u8 {
+    match host {
+        Host::FirefoxWatcher => 2,
+        Host::GnomeExtension => 1,
+        Host::UserDaemon | Host::GnomeApplication => 0,
+    }
+}
+
+/// Select the hosts whose sessions count, given the hosts with an active
+/// session and the moment at which each of them was focused.
+pub fn counting_hosts(policy: AggregationPolicy, sessions: &[(Host, Stamp)]) -> Vec<Host> {
+    let selected = match policy {
+        AggregationPolicy::All => return sessions.iter().map(|(host, _)| host.clone()).collect(),
+        AggregationPolicy::Foreground => sessions.iter()
+            .max_by_key(|(host, focused_at)| (*focused_at, specificity(host))),
+        AggregationPolicy::MostSpecific => sessions.iter()
+            .max_by_key(|(host, focused_at)| (specificity(host), *focused_at)),
+    };
+
+    selected.map(|(host, _)| vec![host.clone()]).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{ManualClock, TrustedClock, JumpPolicy};
+    use chrono::Utc;
+
+    /// The stamps of two sessions, the browser focused before the desktop.
+    fn sessions() -> Vec<(Host, Stamp)> {
+        let source = ManualClock::new(Utc::now());
+        let clock = TrustedClock::new(source.clone(), JumpPolicy::Monotonic);
+        let browser = clock.stamp();
+        source.advance(5);
+        let desktop = clock.stamp();
+        vec![(Host::FirefoxWatcher, browser), (Host::GnomeExtension, desktop)]
+    }
+
+    #[test]
+    fn all_counts_every_host() {
+        let hosts = counting_hosts(AggregationPolicy::All, &sessions());
+        assert_eq!(hosts, vec![Host::FirefoxWatcher, Host::GnomeExtension]);
+    }
+
+    #[test]
+    fn foreground_counts_the_last_focused_host() {
+        let hosts = counting_hosts(AggregationPolicy::Foreground, &sessions());
+        assert_eq!(hosts, vec![Host::GnomeExtension]);
+    }
+
+    #[test]
+    fn most_specific_prefers_the_browser() {
+        let hosts = counting_hosts(AggregationPolicy::MostSpecific, &sessions());
+        assert_eq!(hosts, vec![Host::FirefoxWatcher]);
+    }
+
+    #[test]
+    fn nothing_counts_without_sessions() {
+        assert!(counting_hosts(AggregationPolicy::Foreground, &[]).is_empty());
+    }
+}
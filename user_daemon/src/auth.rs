@@ -0,0 +1,163 @@
+use argon2::Argon2;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::password_hash::rand_core::OsRng;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+/// The number of failed attempts that are allowed before any delay applies.
+const FREE_ATTEMPTS: u32 = 3;
+/// The delay after the first penalized attempt. Each further failure doubles it.
+const BASE_DELAY_SECS: i64 = 5;
+/// The upper bound on the delay between two attempts.
+const MAX_DELAY_SECS: i64 = 15 * 60;
+
+/// The parameters of the partner codes. These match the defaults of
+/// common authenticator apps, so the secret can be imported directly.
+const PARTNER_CODE_DIGITS: usize = 6;
+const PARTNER_CODE_STEP: u64 = 30;
+/// The number of steps on either side of the current one that are still
+/// accepted, to tolerate drift between the clocks of the two devices.
+const PARTNER_CODE_SKEW: u64 = 1;
+const PARTNER_ISSUER: &str = "Activity Warden";
+const PARTNER_ACCOUNT: &str = "partner";
+
+/// The outcome of comparing a password against the stored hash.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verification {
+    Invalid,
+    Valid,
+    /// The password matches a legacy, unsalted SHA-256 digest.
+    /// The stored hash should be replaced with `hash_password`.
+    ValidLegacy,
+}
+
+/// Hash a raw password into a salted Argon2id PHC string.
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Failed to hash the password")
+        .to_string()
+}
+
+/// Compare a raw password against a stored hash in constant time.
+/// Both PHC strings and the legacy hex-encoded SHA-256 digests are accepted.
+pub fn verify_password(password: &str, stored_hash: &str) -> Verification {
+    if let Ok(parsed) = PasswordHash::new(stored_hash) {
+        return match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => Verification::Valid,
+            Err(_) => Verification::Invalid,
+        };
+    }
+
+    let digest = hex::encode(Sha256::digest(password.as_bytes()));
+    if bool::from(digest.as_bytes().ct_eq(stored_hash.trim().as_bytes())) {
+        Verification::ValidLegacy
+    } else {
+        Verification::Invalid
+    }
+}
+
+/// Generate a new base32-encoded secret to share with an accountability partner.
+pub fn generate_partner_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+/// Fails if the stored secret is corrupt, i.e. not base32 or too short.
+fn partner_totp(secret: &str) -> Result<TOTP, String> {
+    let bytes = Secret::Encoded(secret.trim().to_string())
+        .to_bytes()
+        .map_err(|e| format!("The partner secret is not valid base32: {:?}", e))?;
+    TOTP::new(
+        Algorithm::SHA1,
+        PARTNER_CODE_DIGITS,
+        PARTNER_CODE_SKEW as u8,
+        PARTNER_CODE_STEP,
+        bytes,
+        Some(PARTNER_ISSUER.to_string()),
+        PARTNER_ACCOUNT.to_string(),
+    ).map_err(|e| format!("The partner secret is unusable: {}", e))
+}
+
+/// The `otpauth://` URL of a partner secret, to be imported into an authenticator app.
+pub fn partner_url(secret: &str) -> Result<String, String> {
+    Ok(partner_totp(secret)?.get_url())
+}
+
+/// Compare a code against the partner secret at the given Unix time.
+/// Returns the time step that the code belongs to, so that a caller
+/// can refuse to accept the same code twice.
+pub fn verify_partner_code(secret: &str, code: &str, unix_time: u64) -> Result<Option<u64>, String> {
+    let totp = partner_totp(secret)?;
+    let cur_step = unix_time / PARTNER_CODE_STEP;
+    Ok((cur_step.saturating_sub(PARTNER_CODE_SKEW)..=cur_step + PARTNER_CODE_SKEW)
+        .find(|step| {
+            let expected = totp.generate(step * PARTNER_CODE_STEP);
+            bool::from(expected.as_bytes().ct_eq(code.trim().as_bytes()))
+        }))
+}
+
+/// Tracks failed password attempts and enforces an exponential
+/// delay between attempts once the free attempts are exhausted.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Backoff {
+    failures: u32,
+    retry_after: Option<DateTime<Utc>>,
+}
+
+impl Backoff {
+    /// The number of seconds until the next attempt is permitted, if any.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<i64> {
+        self.retry_after
+            .map(|t| (t - now).num_seconds())
+            .filter(|&secs| secs > 0)
+    }
+
+    pub fn record_failure(&mut self, now: DateTime<Utc>) {
+        self.failures += 1;
+        if self.failures > FREE_ATTEMPTS {
+            let exponent = (self.failures - FREE_ATTEMPTS - 1).min(16);
+            let delay = (BASE_DELAY_SECS << exponent).min(MAX_DELAY_SECS);
+            self.retry_after = Some(now + Duration::seconds(delay));
+        }
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.retry_after = None;
+    }
+}
+
+/// The state of the authentication that survives a restart, so that
+/// a restart neither clears the delay nor accepts a used partner code again.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct AuthState {
+    /// The failed attempts used to delay further attempts.
+    pub backoff: Backoff,
+    /// The time step of the last accepted partner code, so that a code is only used once.
+    pub last_partner_step: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_partner_code_is_accepted_within_its_step() {
+        let secret = generate_partner_secret();
+        let code = partner_totp(&secret).unwrap().generate(PARTNER_CODE_STEP * 1000);
+        assert_eq!(verify_partner_code(&secret, &code, PARTNER_CODE_STEP * 1000), Ok(Some(1000)));
+        assert_eq!(verify_partner_code(&secret, "000000x", PARTNER_CODE_STEP * 1000), Ok(None));
+    }
+
+    #[test]
+    fn a_corrupt_partner_secret_is_an_error() {
+        assert!(verify_partner_code("not base32!", "123456", 0).is_err());
+        assert!(verify_partner_code("AAAA", "123456", 0).is_err());
+        assert!(partner_url("").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use chrono::{NaiveDate, Utc};
+use log::info;
+use std::collections::HashSet;
+
+use shared::dbus::Host;
+use shared::types::backup::{Backup, BackupFormat, BackupSettings, ImportSummary, BACKUP_VERSION};
+use shared::types::day;
+use shared::types::schema::{DailySummary, FocusChange};
+use crate::persistence::PersistenceLayer;
+
+/// A display name on a tracking day, which either its raw sessions or its daily summary account for.
+type DayKey = (String, Host, NaiveDate);
+
+fn session_day(fc: &FocusChange, day_start_hour: u32) -> DayKey {
+    (fc.display_name.clone(), fc.host.clone(), day::tracking_day(fc.timestamp, day_start_hour))
+}
+
+fn summary_day(summary: &DailySummary) -> DayKey {
+    (summary.display_name.clone(), summary.host.clone(), summary.day)
+}
+
+/// Serialize the contents of the database in the requested format.
+/// The CSV format only contains the raw focus changes. The password hash is never
+/// exported, so that a backup cannot be used to guess the password offline.
+pub fn export_data(pl: &PersistenceLayer, format: BackupFormat) -> Result<String, String> {
+    match format {
+        BackupFormat::Json => {
+            let backup = Backup {
+                version: BACKUP_VERSION,
+                exported_at: Utc::now(),
+                timers: pl.select_timers(),
+                focus_changes: pl.select_all_focus_changes(),
+                daily_summaries: pl.select_daily_summaries(),
+                settings: BackupSettings::default(),
+            };
+
+            serde_json::to_string_pretty(&backup).map_err(|e| e.to_string())
+        },
+        BackupFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for fc in pl.select_all_focus_changes() {
+                writer.serialize(fc).map_err(|e| e.to_string())?;
+            }
+
+            let bytes = writer.into_inner().map_err(|e| e.to_string())?;
+            String::from_utf8(bytes).map_err(|e| e.to_string())
+        },
+    }
+}
+
+/// Merge previously exported data into the database. Existing records
+/// always take precedence, so importing the same data twice is a no-op.
+/// A display name is either accounted for by its raw sessions or by its daily summary
+/// on a given day, never both, so that a day compacted since the backup is not counted twice.
+pub fn import_data(
+    pl: &PersistenceLayer,
+    format: BackupFormat,
+    data: &str,
+    day_start_hour: u32,
+) -> Result<ImportSummary, String> {
+    let mut summary = ImportSummary::default();
+    let focus_changes = match format {
+        BackupFormat::Json => {
+            let backup: Backup = serde_json::from_str(data).map_err(|e| e.to_string())?;
+            if backup.version > BACKUP_VERSION {
+                return Err(format!(
+                    "Backup version {} is newer than the supported version {}",
+                    backup.version, BACKUP_VERSION
+                ));
+            }
+
+            for timer in &backup.timers {
+                if timer.allowed_days.len() != 7 {
+                    return Err(format!("The timer for '{}' does not specify 7 days", timer.display_name));
+                }
+            }
+
+            for timer in &backup.timers {
+                if pl.insert_timer_if_absent(timer) {
+                    summary.timers_imported += 1;
+                } else {
+                    summary.timers_skipped += 1;
+                }
+            }
+
+            // Only backups of earlier versions carry a hash. Never replace a configured
+            // password, otherwise an import could unlock the daemon.
+            if let Some(password_hash) = backup.settings.password_hash
+                && pl.get_cur_password().is_none() {
+                pl.set_password_hash(&password_hash);
+                summary.password_imported = true;
+            }
+
+            let raw_days = pl.select_all_focus_changes()
+                .iter()
+                .map(|fc| session_day(fc, day_start_hour))
+                .collect::<HashSet<DayKey>>();
+            let total = backup.daily_summaries.len() as u32;
+            let daily_summaries = backup.daily_summaries.into_iter()
+                .filter(|s| !raw_days.contains(&summary_day(s)))
+                .collect::<Vec<_>>();
+            summary.daily_summaries_imported = pl.insert_daily_summaries_if_absent(&daily_summaries);
+            summary.daily_summaries_skipped = total - summary.daily_summaries_imported;
+
+            backup.focus_changes
+        },
+        BackupFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(data.as_bytes());
+            reader.deserialize::<FocusChange>()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?
+        },
+    };
+
+    let compacted_days = pl.select_daily_summaries()
+        .iter()
+        .map(summary_day)
+        .collect::<HashSet<DayKey>>();
+    let total = focus_changes.len() as u32;
+    let focus_changes = focus_changes.into_iter()
+        .filter(|fc| !compacted_days.contains(&session_day(fc, day_start_hour)))
+        .collect::<Vec<_>>();
+    summary.focus_changes_imported = pl.insert_focus_changes_if_absent(&focus_changes);
+    summary.focus_changes_skipped = total - summary.focus_changes_imported;
+
+    info!(
+        "[IMPORT] Imported {} timers, {} focus changes and {} daily summaries ({}, {} and {} already present).",
+        summary.timers_imported,
+        summary.focus_changes_imported,
+        summary.daily_summaries_imported,
+        summary.timers_skipped,
+        summary.focus_changes_skipped,
+        summary.daily_summaries_skipped,
+    );
+    Ok(summary)
+}
@@ -0,0 +1,257 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use shared::types::day;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// The largest difference between the wall clock and the elapsed time that is
+/// attributed to scheduling noise rather than a change of the clock.
+pub const MAX_CLOCK_DRIFT_SECS: i64 = 60;
+
+/// A source of the current time.
+pub trait Clock: Send + Sync {
+    /// The time according to the system clock, which the user may change at will.
+    fn wall(&self) -> DateTime<Utc>;
+    /// The time elapsed since an arbitrary, fixed point. It never goes back
+    /// and keeps counting while the system is suspended.
+    fn elapsed(&self) -> std::time::Duration;
+}
+
+/// The clocks of the operating system.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn wall(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn elapsed(&self) -> std::time::Duration {
+        // Unlike `Instant`, which is backed by `CLOCK_MONOTONIC`, the boot time
+        // includes suspends, so a suspend is not mistaken for a change of the clock.
+        let mut ts = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+        let res = unsafe { libc::clock_gettime(libc::CLOCK_BOOTTIME, &mut ts) };
+        assert_eq!(res, 0, "Failed to read the boot time clock.");
+        std::time::Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32)
+    }
+}
+
+/// How a jump of the wall clock affects the time used by the daemon, and with it the day boundary.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JumpPolicy {
+    /// Keep counting from the elapsed time, so that a jump neither starts nor repeats a day.
+    /// The wall clock is only trusted again once the daemon restarts.
+    #[default]
+    Monotonic,
+    /// Follow the wall clock to its new time. The day boundary still never moves
+    /// back, so setting the clock back does not grant the budget of a day again.
+    Wall,
+}
+
+/// A change of the wall clock that is not explained by the elapsed time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClockJump {
+    /// The wall clock time that was expected from the elapsed time.
+    pub expected: DateTime<Utc>,
+    /// The wall clock time that was observed instead.
+    pub observed: DateTime<Utc>,
+}
+
+impl ClockJump {
+    /// The size of the jump in seconds, negative if the clock moved back.
+    pub fn seconds(&self) -> i64 {
+        (self.observed - self.expected).num_seconds()
+    }
+}
+
+/// A point on the elapsed time of a `TrustedClock`, used to measure durations
+/// that are unaffected by changes of the wall clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Stamp(std::time::Duration);
+
+/// The time as the daemon sees it: the wall clock at startup, advanced by the elapsed time.
+/// Jumps of the wall clock are detected by `check` and handled according to the `JumpPolicy`.
+#[derive(Clone)]
+pub struct TrustedClock {
+    source: Arc<dyn Clock>,
+    policy: JumpPolicy,
+    /// The trusted time at `base_elapsed`.
+    base_time: DateTime<Utc>,
+    base_elapsed: std::time::Duration,
+    /// The wall clock at `wall_elapsed`, as of the last check.
+    last_wall: DateTime<Utc>,
+    wall_elapsed: std::time::Duration,
+}
+
+impl TrustedClock {
+    pub fn new(source: Arc<dyn Clock>, policy: JumpPolicy) -> Self {
+        let wall = source.wall();
+        let elapsed = source.elapsed();
+        Self {
+            source,
+            policy,
+            base_time: wall,
+            base_elapsed: elapsed,
+            last_wall: wall,
+            wall_elapsed: elapsed,
+        }
+    }
+
+    pub fn system(policy: JumpPolicy) -> Self {
+        Self::new(Arc::new(SystemClock), policy)
+    }
+
+    /// The current trusted time.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.base_time + self.since(self.base_elapsed)
+    }
+
+    /// The current trusted tracking day, which starts `day_start_hour` hours after local midnight.
+    pub fn today(&self, day_start_hour: u32) -> NaiveDate {
+        day::tracking_day(self.now(), day_start_hour)
+    }
+
+    pub fn stamp(&self) -> Stamp {
+        Stamp(self.source.elapsed())
+    }
+
+    /// The whole seconds elapsed since `stamp`.
+    pub fn seconds_since(&self, stamp: Stamp) -> u32 {
+        self.since(stamp.0).num_seconds().clamp(0, u32::MAX as i64) as u32
+    }
+
+    /// Compare the wall clock against the elapsed time since the last check,
+    /// and report a jump that exceeds the tolerated drift.
+    pub fn check(&mut self) -> Option<ClockJump> {
+        let wall = self.source.wall();
+        let expected = self.last_wall + self.since(self.wall_elapsed);
+        self.last_wall = wall;
+        self.wall_elapsed = self.source.elapsed();
+
+        if (wall - expected).num_seconds().abs() <= MAX_CLOCK_DRIFT_SECS {
+            return None;
+        }
+
+        if self.policy == JumpPolicy::Wall {
+            self.base_time = wall;
+            self.base_elapsed = self.wall_elapsed;
+        }
+        Some(ClockJump { expected, observed: wall })
+    }
+
+    fn since(&self, elapsed: std::time::Duration) -> Duration {
+        Duration::from_std(self.source.elapsed().saturating_sub(elapsed)).unwrap_or(Duration::MAX)
+    }
+}
+
+/// A clock that only moves when told to, so that tests run deterministically.
+pub struct ManualClock {
+    state: Mutex<(DateTime<Utc>, std::time::Duration)>,
+}
+
+impl ManualClock {
+    pub fn new(wall: DateTime<Utc>) -> Arc<Self> {
+        Arc::new(Self { state: Mutex::new((wall, std::time::Duration::ZERO)) })
+    }
+
+    /// Let time pass on both clocks.
+    pub fn advance(&self, secs: i64) {
+        let mut state = self.state.lock().unwrap();
+        state.0 += Duration::seconds(secs);
+        state.1 += std::time::Duration::from_secs(secs as u64);
+    }
+
+    /// Change the wall clock without any time passing.
+    pub fn set_wall(&self, wall: DateTime<Utc>) {
+        self.state.lock().unwrap().0 = wall;
+    }
+}
+
+impl Clock for ManualClock {
+    fn wall(&self) -> DateTime<Utc> {
+        self.state.lock().unwrap().0
+    }
+
+    fn elapsed(&self) -> std::time::Duration {
+        self.state.lock().unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn follows_the_elapsed_time() {
+        let source = ManualClock::new(start());
+        let mut clock = TrustedClock::new(source.clone(), JumpPolicy::Monotonic);
+        let stamp = clock.stamp();
+
+        source.advance(90);
+        assert_eq!(clock.check(), None);
+        assert_eq!(clock.now(), start() + Duration::seconds(90));
+        assert_eq!(clock.seconds_since(stamp), 90);
+    }
+
+    #[test]
+    fn tolerates_small_drift() {
+        let source = ManualClock::new(start());
+        let mut clock = TrustedClock::new(source.clone(), JumpPolicy::Wall);
+
+        source.advance(30);
+        source.set_wall(start() + Duration::seconds(30 + MAX_CLOCK_DRIFT_SECS));
+        assert_eq!(clock.check(), None);
+        assert_eq!(clock.now(), start() + Duration::seconds(30));
+    }
+
+    #[test]
+    fn ignores_a_jump_forward() {
+        let source = ManualClock::new(start());
+        let mut clock = TrustedClock::new(source.clone(), JumpPolicy::Monotonic);
+        let stamp = clock.stamp();
+
+        source.set_wall(start() + Duration::days(1));
+        let jump = clock.check().expect("The jump was not detected.");
+        assert_eq!(jump.seconds(), 24 * 60 * 60);
+        assert_eq!(clock.now(), start());
+        assert_eq!(clock.seconds_since(stamp), 0);
+
+        // The jump is only reported once.
+        source.advance(10);
+        assert_eq!(clock.check(), None);
+        assert_eq!(clock.now(), start() + Duration::seconds(10));
+    }
+
+    #[test]
+    fn ignores_a_jump_back() {
+        let source = ManualClock::new(start());
+        let mut clock = TrustedClock::new(source.clone(), JumpPolicy::Monotonic);
+        let stamp = clock.stamp();
+
+        source.advance(60);
+        source.set_wall(start() - Duration::hours(3));
+        let jump = clock.check().expect("The jump was not detected.");
+        assert_eq!(jump.seconds(), -(3 * 60 * 60 + 60));
+        assert_eq!(clock.now(), start() + Duration::seconds(60));
+        assert_eq!(clock.seconds_since(stamp), 60);
+    }
+
+    #[test]
+    fn follows_a_jump_with_the_wall_policy() {
+        let source = ManualClock::new(start());
+        let mut clock = TrustedClock::new(source.clone(), JumpPolicy::Wall);
+        let stamp = clock.stamp();
+
+        source.advance(60);
+        source.set_wall(start() - Duration::hours(3));
+        assert!(clock.check().is_some());
+        assert_eq!(clock.now(), start() - Duration::hours(3));
+
+        // Durations are measured on the elapsed time regardless of the policy.
+        assert_eq!(clock.seconds_since(stamp), 60);
+    }
+}
@@ -0,0 +1,330 @@
+use directories::BaseDirs;
+use log::{info, warn, LevelFilter};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::AggregationPolicy;
+use crate::clock::JumpPolicy;
+use std::fs;
+use std::path::PathBuf;
+
+const CONFIG_DIR: &str = "activity_warden";
+const CONFIG_FILE_NAME: &str = "config.toml";
+/// The directory of the daemon's state within the XDG data directory.
+const STORE_DIR: &str = "activity_warden";
+/// The name of the SQLite DB file used by the daemon.
+const DB_FILE_NAME: &str = "aw_records.db3";
+
+/// The user-tunable behaviour of the daemon. The configuration is read from
+/// `config.toml` within the XDG config directory. Missing fields take their defaults.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct DaemonConfig {
+    pub retention: RetentionConfig,
+    pub lock: LockConfig,
+    pub clock: ClockConfig,
+    pub tracking: TrackingConfig,
+    pub warnings: WarningConfig,
+    pub storage: StorageConfig,
+    pub logging: LoggingConfig,
+    pub daemon: RuntimeConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Raw sessions older than this many days are rolled up into daily summaries,
+    /// which deletes them. A value of 0, the default, keeps every raw session.
+    pub raw_days: u32,
+    /// The minimum number of days between two vacuums of the database.
+    pub vacuum_interval_days: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct LockConfig {
+    /// The minutes that an unlock, or a change that loosens a timer, waits before
+    /// it takes effect. Such changes can be cancelled in the meantime.
+    /// A value of 0 applies every change immediately.
+    pub cooling_off_minutes: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// How a change of the system clock affects the day boundary.
+    pub jump_policy: JumpPolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct TrackingConfig {
+    /// How the time is counted while several watchers report an active session at once.
+    pub aggregation: AggregationPolicy,
+    /// The minutes without any input after which the sessions in progress end, without
+    /// the idle time. A value of 0 keeps counting regardless of input.
+    pub idle_threshold_minutes: u32,
+    /// The local hour at which a new day starts, so that the time spent late at night
+    /// still counts towards the previous day. A value of 0 starts the day at midnight.
+    pub day_start_hour: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct WarningConfig {
+    /// The minutes before a display name is blocked at which a notification warns about it.
+    pub minutes_before: Vec<u32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct StorageConfig {
+    /// The directory of the database and the remaining state of the daemon.
+    /// Defaults to `activity_warden` within the XDG data directory.
+    pub data_dir: Option<PathBuf>,
+    /// The database file, relative to `data_dir` unless absolute.
+    pub database: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// The most detailed level that is logged. `RUST_LOG` takes precedence if it is set.
+    pub level: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct RuntimeConfig {
+    /// The number of events that may wait for the daemon. Beyond it, heartbeats and
+    /// superseded events are dropped first, so the latest focus of every host is kept.
+    pub event_capacity: usize,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            raw_days: 0,
+            vacuum_interval_days: 7,
+        }
+    }
+}
+
+impl Default for WarningConfig {
+    fn default() -> Self {
+        Self {
+            minutes_before: vec![5, 1],
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            data_dir: None,
+            database: PathBuf::from(DB_FILE_NAME),
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+        }
+    }
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            event_capacity: 100,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// The directory of the daemon's state.
+    pub fn data_root(&self) -> PathBuf {
+        self.data_dir.clone().unwrap_or_else(|| {
+            let base_dirs = BaseDirs::new().unwrap();
+            base_dirs.data_dir().join(STORE_DIR)
+        })
+    }
+
+    pub fn database_path(&self) -> PathBuf {
+        self.data_root().join(&self.database)
+    }
+}
+
+impl LoggingConfig {
+    pub fn level_filter(&self) -> LevelFilter {
+        self.level.parse().unwrap_or(LevelFilter::Info)
+    }
+
+    /// Log at the configured level, unless `RUST_LOG` chose the filters instead.
+    pub fn apply(&self) {
+        if std::env::var_os("RUST_LOG").is_none() {
+            log::set_max_level(self.level_filter());
+        }
+    }
+}
+
+impl DaemonConfig {
+    pub fn path() -> PathBuf {
+        let base_dirs = BaseDirs::new().unwrap();
+        base_dirs.config_dir().join(CONFIG_DIR).join(CONFIG_FILE_NAME)
+    }
+
+    /// Load the configuration file, falling back to the defaults if
+    /// the file does not exist or is invalid.
+    pub fn load() -> Self {
+        match Self::read() {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("[CONFIG] Using the defaults: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Read and validate the configuration file. A missing file yields the defaults.
+    pub fn read() -> Result<Self, String> {
+        let path = Self::path();
+        if !path.exists() {
+            info!("[CONFIG] No configuration at {:?}, using the defaults.", path);
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        let config = Self::parse(&contents)
+            .map_err(|e| format!("Invalid configuration in {:?}: {}", path, e))?;
+        info!("[CONFIG] Loaded the configuration from {:?}.", path);
+        Ok(config)
+    }
+
+    /// Parse and validate the contents of a configuration file.
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(contents).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Replace the configuration file with `contents`, which must be valid.
+    pub fn write(contents: &str) -> Result<Self, String> {
+        let config = Self::parse(contents)?;
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        Ok(config)
+    }
+
+    /// The contents of the configuration file, or the defaults if there is none.
+    pub fn contents() -> Result<String, String> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default().to_toml());
+        }
+        fs::read_to_string(&path).map_err(|e| format!("Failed to read {:?}: {}", path, e))
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("The configuration is always serializable.")
+    }
+
+    /// Check the values that the types alone do not constrain. Every problem is reported at once.
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+        if self.logging.level.parse::<LevelFilter>().is_err() {
+            problems.push(format!(
+                "logging.level '{}' must be one of off, error, warn, info, debug or trace",
+                self.logging.level,
+            ));
+        }
+        if self.tracking.day_start_hour > 23 {
+            problems.push("tracking.day_start_hour must be between 0 and 23".to_string());
+        }
+        if self.warnings.minutes_before.contains(&0) {
+            problems.push("warnings.minutes_before must only contain positive minutes".to_string());
+        }
+        if self.storage.data_dir.as_ref().is_some_and(|dir| !dir.is_absolute()) {
+            problems.push("storage.data_dir must be an absolute path".to_string());
+        }
+        if self.storage.database.as_os_str().is_empty() {
+            problems.push("storage.database must not be empty".to_string());
+        }
+        if self.daemon.event_capacity == 0 {
+            problems.push("daemon.event_capacity must be at least 1".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+
+    /// The sections of `other` that differ from this configuration but are only read at startup.
+    pub fn restart_required(&self, other: &DaemonConfig) -> Vec<&'static str> {
+        let mut sections = Vec::new();
+        if self.clock != other.clock {
+            sections.push("clock");
+        }
+        if self.storage != other.storage {
+            sections.push("storage");
+        }
+        if self.daemon != other.daemon {
+            sections.push("daemon");
+        }
+        sections
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_take_their_defaults() {
+        let config = DaemonConfig::parse("[tracking]\nidle_threshold_minutes = 10\n").unwrap();
+        assert_eq!(config.tracking.idle_threshold_minutes, 10);
+        assert_eq!(config.retention, RetentionConfig::default());
+        assert_eq!(config.warnings.minutes_before, vec![5, 1]);
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let contents = "[logging]\nlevel = \"loud\"\n[daemon]\nevent_capacity = 0\n";
+        let e = DaemonConfig::parse(contents).unwrap_err();
+        assert!(e.contains("logging.level"), "{}", e);
+        assert!(e.contains("daemon.event_capacity"), "{}", e);
+    }
+
+    #[test]
+    fn the_day_starts_within_a_day() {
+        assert_eq!(DaemonConfig::parse("[tracking]\nday_start_hour = 4\n").unwrap().tracking.day_start_hour, 4);
+        assert!(DaemonConfig::parse("[tracking]\nday_start_hour = 24\n").is_err());
+    }
+
+    #[test]
+    fn unknown_values_are_rejected() {
+        assert!(DaemonConfig::parse("[tracking]\naggregation = \"sometimes\"\n").is_err());
+    }
+
+    #[test]
+    fn the_defaults_round_trip() {
+        let config = DaemonConfig::default();
+        assert_eq!(DaemonConfig::parse(&config.to_toml()).unwrap(), config);
+    }
+
+    #[test]
+    fn only_startup_sections_require_a_restart() {
+        let config = DaemonConfig::default();
+        let mut other = config.clone();
+        other.tracking.aggregation = AggregationPolicy::Foreground;
+        other.daemon.event_capacity = 10;
+        assert_eq!(config.restart_required(&other), vec!["daemon"]);
+    }
+}
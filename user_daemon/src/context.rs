@@ -1,22 +1,42 @@
 use std::collections::HashMap;
 use std::sync::Arc;
-use zbus::{Result, object_server::SignalEmitter, interface};
+use zbus::{Result, fdo, message::Header, object_server::SignalEmitter, interface};
 use arc_swap::ArcSwap;
-use chrono::{DateTime, NaiveDate, Local, Utc};
-use sha2::{Sha256, Digest};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
 
 use shared::types::schema::{FocusChange, QueryType, Timer};
-use shared::types::daemon::{DurationMap, DaemonSnapshot};
-use shared::types::Event;
+use shared::types::daemon::{
+    ActiveSession, Coverage, DurationMap, DaemonSnapshot, GapKind, PendingChanges, PendingTimerChange,
+    SessionPage, TimerAction, UnlockEvent, UnlockMethod, WatcherStatus,
+};
+use shared::types::backup::{BackupFormat, ImportSummary};
+use shared::types::{day, Event, EventType};
 use shared::dbus::Host;
+use crate::aggregation;
+use crate::auth::{self, AuthState, Verification};
+use crate::backup;
+use crate::config::DaemonConfig;
+use crate::clock::{Stamp, TrustedClock};
+use crate::coverage::{self, WatcherState, LIVENESS_TIMEOUT_SECS};
+use crate::pending::{self, Change, PendingChange};
 use crate::persistence::PersistenceLayer;
-use crate::EventType;
-use log::{info, debug};
+use log::{info, debug, warn};
+
+/// The largest number of sessions returned by a single page.
+const MAX_SESSION_PAGE_SIZE: u32 = 1000;
+/// The number of seconds that an edit session remains valid while locked.
+const EDIT_SESSION_SECS: i64 = 5 * 60;
 
 #[derive(Clone)]
 pub struct LastEvent {
     pub time: DateTime<Utc>,
-    pub event: Event
+    pub event: Event,
+    /// The moment the host was focused, which orders the hosts for the aggregation policy.
+    pub focused_at: Stamp,
+    /// The seconds counted towards the session before `counting_since`.
+    pub counted: u32,
+    /// The moment from which the session counts, unless the aggregation policy paused it.
+    pub counting_since: Option<Stamp>,
 }
 
 /// The `DaemonContext` maintains the state of the `User Daemon`.
@@ -37,41 +57,348 @@ pub struct DaemonContext {
     /// This value is necessary to determine when state should be wiped
     /// on a new day.
     cur_date: NaiveDate,
+    /// The behaviour configured by the user.
+    config: DaemonConfig,
+    /// The date of the last vacuum of the database, if any since startup.
+    last_vacuum: Option<NaiveDate>,
+    /// The failed password attempts and the last accepted partner code,
+    /// stored along with the lock state whenever they change.
+    auth: AuthState,
+    /// The expiry of the edit sessions granted while locked, keyed by
+    /// the unique bus name of the authenticated client.
+    edit_sessions: HashMap<String, DateTime<Utc>>,
+    /// The liveness of every watcher seen since startup.
+    watchers: HashMap<Host, WatcherState>,
+    /// The source of the current time, which is robust against changes of the system clock.
+    clock: TrustedClock,
 }
 
 impl DaemonContext {
-    pub fn new() -> Self {
-        let pl = PersistenceLayer::new();
+    pub fn new(config: DaemonConfig) -> Self {
+        let clock = TrustedClock::system(config.clock.jump_policy);
+        let pl = PersistenceLayer::new(&config.storage);
+        Self::with_parts(config, clock, pl)
+    }
+
+    /// Assemble a context from an explicit clock and database, i.e. to drive it in tests.
+    pub fn with_parts(config: DaemonConfig, clock: TrustedClock, pl: PersistenceLayer) -> Self {
+        // The cooling-off period and the day start that were in force stay so until a change
+        // to them applies, so that editing the file while the daemon is stopped skips nothing.
+        let mut in_force = config.clone();
+        if let Some((cooling_off_minutes, day_start_hour)) = pl.get_settings_in_force() {
+            in_force.lock.cooling_off_minutes = cooling_off_minutes;
+            in_force.tracking.day_start_hour = day_start_hour;
+        }
+
+        // Record the sessions that were in progress when the daemon last stopped.
+        for fc in pl.recover_active_sessions() {
+            info!("[RECOVERY] Recovered {} seconds on '{}' from {}.", fc.duration, fc.display_name, fc.host);
+        }
+
         let timers = pl.select_timers();
-        let durations = pl.select_current_durations();
+        let day_start_hour = in_force.tracking.day_start_hour;
+        let today = clock.today(day_start_hour);
+        let durations = pl.select_current_durations(today, day_start_hour);
 
-        let today = Utc::now().date_naive();
-        Self {
+        let mut context = Self {
             pl: pl.clone(),
             timers: Arc::new(ArcSwap::from_pointee(timers)),
             timer_durations: durations,
             last_event: HashMap::new(),
-            cur_date: today
+            cur_date: today,
+            config: in_force,
+            last_vacuum: None,
+            auth: pl.get_auth_state(),
+            edit_sessions: HashMap::new(),
+            watchers: HashMap::new(),
+            clock,
+        };
+        // The file is applied like a reload, so any loosening change in it is held back.
+        context.apply_config(config, false);
+        context
+    }
+
+    /// The current time, as trusted by the daemon.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// The current tracking day, which starts at the configured hour.
+    /// It never precedes the day being tracked, even if the clock was set back.
+    pub fn today(&self) -> NaiveDate {
+        self.clock.today(self.config.tracking.day_start_hour).max(self.cur_date)
+    }
+
+    pub fn config(&self) -> &DaemonConfig {
+        &self.config
+    }
+
+    /// Replace the configuration while the daemon runs. The sections that are only read
+    /// at startup keep their values, and the changes that loosen the restrictions are held
+    /// back: a shorter cooling-off period and a new day start are queued for the current
+    /// period, and while locked, the day start only moves if `may_edit` is set, i.e. because
+    /// the caller holds an edit session. Returns whether the pending changes changed.
+    pub fn apply_config(&mut self, mut config: DaemonConfig, may_edit: bool) -> bool {
+        for section in self.config.restart_required(&config) {
+            warn!("[CONFIG] The [{}] section applies after a restart.", section);
+        }
+        config.clock = self.config.clock.clone();
+        config.storage = self.config.storage.clone();
+        config.daemon = self.config.daemon.clone();
+
+        let mut pending_changed = self.hold_back_cooling_off(&mut config);
+        pending_changed |= self.hold_back_day_start(&mut config, may_edit);
+
+        config.logging.apply();
+        let day_start_moved = config.tracking.day_start_hour != self.config.tracking.day_start_hour;
+        self.config = config;
+        self.store_settings_in_force();
+        if day_start_moved {
+            self.follow_day_start();
+        }
+        pending_changed
+    }
+
+    /// Queue a shorter cooling-off period for the current one, as it loosens every
+    /// restriction that it delays, and keep the current one in `config` meanwhile.
+    /// Returns whether the pending changes changed.
+    fn hold_back_cooling_off(&self, config: &mut DaemonConfig) -> bool {
+        let requested = config.lock.cooling_off_minutes;
+        let current = self.config.lock.cooling_off_minutes;
+        let Some(delay) = self.cooling_off().filter(|_| requested < current) else {
+            // The period in force or a longer one supersedes any pending reduction.
+            return self.discard_pending(|c| matches!(c, Change::CoolingOff { .. }));
+        };
+
+        config.lock.cooling_off_minutes = current;
+        // A repeated request keeps the original schedule rather than restarting it.
+        if self.pending_change(|c| matches!(c, Change::CoolingOff { minutes } if *minutes == requested)) {
+            return false;
+        }
+        self.discard_pending(|c| matches!(c, Change::CoolingOff { .. }));
+        warn!("[CONFIG] The cooling-off period is shortened to {} minutes once the current one elapsed.", requested);
+        self.queue_change(Change::CoolingOff { minutes: requested }, delay);
+        true
+    }
+
+    /// Hold back a new day start, which may grant a second budget on the same day: while
+    /// locked, it requires `may_edit`, and it waits for the cooling-off period. The current
+    /// day start stays in `config` meanwhile. Returns whether the pending changes changed.
+    fn hold_back_day_start(&self, config: &mut DaemonConfig, may_edit: bool) -> bool {
+        let requested = config.tracking.day_start_hour;
+        let current = self.config.tracking.day_start_hour;
+        if requested == current {
+            return self.discard_pending(|c| matches!(c, Change::DayStart { .. }));
+        }
+
+        if !may_edit && self.pl.get_lock_state() {
+            warn!("[CONFIG] The day start stays at {}:00, as the daemon is locked.", current);
+            config.tracking.day_start_hour = current;
+            return false;
+        }
+        let Some(delay) = self.cooling_off() else {
+            return self.discard_pending(|c| matches!(c, Change::DayStart { .. }));
+        };
+
+        config.tracking.day_start_hour = current;
+        if self.pending_change(|c| matches!(c, Change::DayStart { hour } if *hour == requested)) {
+            return false;
+        }
+        self.discard_pending(|c| matches!(c, Change::DayStart { .. }));
+        warn!("[CONFIG] The day start moves to {}:00 once the cooling-off period elapsed.", requested);
+        self.queue_change(Change::DayStart { hour: requested }, delay);
+        true
+    }
+
+    /// Remember the cooling-off period and the day start in force across restarts.
+    fn store_settings_in_force(&self) {
+        self.pl.set_settings_in_force(self.config.lock.cooling_off_minutes, self.config.tracking.day_start_hour);
+    }
+
+    /// Take the tracking day and its durations anew after the day start moved,
+    /// as the time already recorded may fall on a different day now.
+    fn follow_day_start(&mut self) {
+        let day_start_hour = self.config.tracking.day_start_hour;
+        info!("[CONFIG] The day starts at {}:00 from now on.", day_start_hour);
+        self.cur_date = self.clock.today(day_start_hour);
+        self.timer_durations = self.pl.select_current_durations(self.cur_date, day_start_hour);
+    }
+
+    /// Read the configuration file again. An invalid file leaves the configuration unchanged.
+    /// Returns whether the pending changes changed.
+    pub fn reload_config_file(&mut self, may_edit: bool) -> std::result::Result<bool, String> {
+        let config = DaemonConfig::read()?;
+        Ok(self.apply_config(config, may_edit))
+    }
+
+    /// Record the time since the daemon last ran as a gap, unless it was a mere restart.
+    pub fn record_startup(&mut self) {
+        let now = self.clock.now();
+        if let Some((last_alive, stopped)) = self.pl.get_liveness() {
+            // Gaps left open by a crash end when the daemon stopped observing them.
+            self.pl.close_open_coverage_gaps(last_alive);
+            if now < last_alive {
+                let detail = format!(
+                    "The clock moved back by {} seconds while the daemon was stopped.",
+                    (last_alive - now).num_seconds(),
+                );
+                warn!("[COVERAGE] {}", detail);
+                self.pl.insert_coverage_gap(GapKind::ClockJump, &Host::UserDaemon, now, Some(last_alive), &detail);
+
+                // The day only ever moves forward, so the budget of a day that ended is not granted again.
+                let day_start_hour = self.config.tracking.day_start_hour;
+                let last_day = day::tracking_day(last_alive, day_start_hour);
+                if last_day > self.cur_date {
+                    self.cur_date = last_day;
+                    self.timer_durations = self.pl.select_current_durations(last_day, day_start_hour);
+                }
+            } else if (now - last_alive).num_seconds() > LIVENESS_TIMEOUT_SECS {
+                let detail = if stopped {
+                    "The daemon was stopped."
+                } else {
+                    "The daemon stopped unexpectedly."
+                };
+                warn!("[COVERAGE] {} Nothing was tracked since {}.", detail, last_alive);
+                self.pl.insert_coverage_gap(GapKind::DaemonDown, &Host::UserDaemon, last_alive, Some(now), detail);
+            }
+        }
+        self.pl.set_liveness(now, false);
+    }
+
+    /// Record every session in progress in full and note the clean shutdown,
+    /// so that the next start recovers nothing. Returns the completed sessions.
+    pub fn shutdown(&mut self) -> Vec<FocusChange> {
+        let completed = self.clear_last_event();
+        self.checkpoint_sessions();
+        self.record_shutdown();
+        completed
+    }
+
+    /// Close every open gap and mark the daemon as stopped cleanly.
+    pub fn record_shutdown(&mut self) {
+        let now = self.clock.now();
+        self.pl.close_open_coverage_gaps(now);
+        self.pl.set_liveness(now, true);
+    }
+
+    /// Note that a watcher is alive, closing its gap if the heartbeats had stopped.
+    pub fn record_heartbeat(&mut self, host: &Host) {
+        let now = self.clock.now();
+        let state = self.watchers.entry(host.clone()).or_insert(WatcherState {
+            last_seen: now,
+            connected: true,
+            open_gap: None,
+        });
+        if let Some(id) = state.open_gap.take() {
+            info!("[COVERAGE] {} reconnected after {} seconds.", host, (now - state.last_seen).num_seconds());
+            self.pl.close_coverage_gap(id, now);
+        }
+        state.last_seen = now;
+        state.connected = true;
+    }
+
+    /// Note that a watcher stopped cleanly, so that its silence is not a gap.
+    pub fn record_disconnect(&mut self, host: &Host) {
+        self.record_heartbeat(host);
+        if let Some(state) = self.watchers.get_mut(host) {
+            info!("[COVERAGE] {} disconnected.", host);
+            state.connected = false;
+        }
+    }
+
+    /// Open a gap for every watcher whose heartbeats stopped, record changes of
+    /// the wall clock, and refresh the liveness of the daemon itself.
+    /// Returns whether the wall clock jumped.
+    pub fn check_coverage(&mut self) -> bool {
+        let now = self.clock.now();
+        for (host, state) in self.watchers.iter_mut() {
+            let silent_secs = (now - state.last_seen).num_seconds();
+            if state.connected && state.open_gap.is_none() && silent_secs > LIVENESS_TIMEOUT_SECS {
+                warn!("[COVERAGE] No heartbeat from {} for {} seconds.", host, silent_secs);
+                state.open_gap = Some(self.pl.insert_coverage_gap(
+                    GapKind::WatcherDisconnected,
+                    host,
+                    state.last_seen,
+                    None,
+                    "The watcher stopped sending heartbeats.",
+                ));
+            }
+        }
+
+        let jump = self.clock.check();
+        if let Some(jump) = &jump {
+            let direction = if jump.seconds() > 0 { "forward" } else { "back" };
+            let detail = format!("The clock moved {} by {} seconds.", direction, jump.seconds().abs());
+            warn!("[COVERAGE] {} Following the {:?} policy.", detail, self.config.clock.jump_policy);
+            self.pl.insert_coverage_gap(
+                GapKind::ClockJump,
+                &Host::UserDaemon,
+                jump.expected.min(jump.observed),
+                Some(jump.expected.max(jump.observed)),
+                &detail,
+            );
+        }
+        self.pl.set_liveness(self.clock.now(), false);
+        jump.is_some()
+    }
+
+    /// Around a suspend, the silence of the watchers is expected and not a gap in coverage.
+    pub fn forgive_suspend(&mut self) {
+        let now = self.clock.now();
+        for state in self.watchers.values_mut().filter(|s| s.open_gap.is_none()) {
+            state.last_seen = now;
         }
     }
 
     /// At the dawn of a new day, reset the internally stored durations.
+    /// The sessions in progress are split at the day start, so that each day
+    /// receives its share, and continue on the budget of the new day.
     /// To avoid the case where an event might span a day boundary, 
-    /// utilize the alarm system to wake up the timer at the midnight boundary.
-    pub fn reset_daily_state(&mut self) {
-        let check_date = Local::now().date_naive();
-        if self.cur_date != check_date {
-            info!("[RESET] Resetting internal state of the daemon context.");
-            self.timer_durations.clear();
-            self.last_event.clear();
-            self.cur_date = check_date;
+    /// utilize the alarm system to wake up the timer at the start of the day.
+    /// The day only ever moves forward, so setting the clock back never
+    /// grants the budget of a day that already ended. Returns whether a new day started.
+    pub fn reset_daily_state(&mut self) -> bool {
+        let check_date = self.today();
+        if check_date <= self.cur_date {
+            return false;
         }
+
+        info!("[RESET] Resetting internal state of the daemon context.");
+        let day_start_hour = self.config.tracking.day_start_hour;
+        self.split_sessions_at(day::day_start(check_date, day_start_hour));
+        self.cur_date = check_date;
+        self.timer_durations = self.pl.select_current_durations(check_date, day_start_hour);
+        true
     }
 
 
+    /// Roll raw sessions that fall outside of the retention window into
+    /// daily summaries and periodically reclaim the freed space.
+    pub fn apply_retention(&mut self) {
+        let retention = &self.config.retention;
+        if retention.raw_days == 0 {
+            return;
+        }
+
+        // Only roll up whole days so that a day is never split across both tables.
+        let today = self.today();
+        let day_start_hour = self.config.tracking.day_start_hour;
+        let cutoff = day::day_start(today - Duration::days(retention.raw_days as i64), day_start_hour);
+        self.pl.compact_focus_changes(cutoff, day_start_hour);
+
+        let vacuum_due = self.last_vacuum
+            .is_none_or(|last| today - last >= Duration::days(retention.vacuum_interval_days as i64));
+        if vacuum_due {
+            self.pl.vacuum();
+            self.last_vacuum = Some(today);
+        }
+    }
+
     /// Clear all stored events in particular scenarios.
     /// For example, if the computer shuts down, stop tracking any state.
-    pub fn clear_last_event(&mut self) {
+    /// Returns the sessions that were completed along the way.
+    pub fn clear_last_event(&mut self) -> Vec<FocusChange> {
+        let mut completed = Vec::new();
         for (host, last_event) in self.last_event.clone().into_iter() {
             let event = Event {
                 event_type: EventType::FocusLost,
@@ -79,93 +406,248 @@ impl DaemonContext {
                 display_name: last_event.event.display_name,
                 metadata: last_event.event.metadata,
             };
-            self.update_event_durations(&event, false);
+            completed.extend(self.update_event_durations(&event, false));
+        }
+        completed
+    }
+
+    /// End the sessions in progress once the user went idle `idle_secs` ago,
+    /// so that the idle time does not count.
+    pub fn end_idle_sessions(&mut self, idle_secs: u32) -> Vec<FocusChange> {
+        let idle_since = self.clock.now() - Duration::seconds(idle_secs as i64);
+        let mut completed = Vec::new();
+        for (_, mut last_event) in std::mem::take(&mut self.last_event) {
+            if let Some(since) = last_event.counting_since.take() {
+                let counting = self.clock.seconds_since(since);
+                last_event.counted += counting - counting.min(idle_secs);
+            }
+            completed.push(self.complete_session(&last_event, idle_since.max(last_event.time)));
         }
+        completed
     }
 
+    /// The number of seconds allowed on a display name today, if a timer applies.
+    /// A timer that is disabled on the current day allows no time at all.
+    pub fn time_limit_today(&self, display_name: &str) -> Option<u32> {
+        // In Chrono, Monday = 0 and Sunday = 6. Shift to start the week on Sunday.
+        let weekday = ((self.today().weekday() as usize) + 1) % 7;
+        self.timers.load().iter()
+            .find(|t| t.display_name == display_name)
+            .map(|t| if t.allowed_days[weekday] { t.time_limit } else { 0 })
+    }
 
-    /// Update the corresponding durations for a given event.
-    pub fn update_event_durations(&mut self, event: &Event, set_last_event: bool) -> Option<FocusChange> {
-        let now = Utc::now();
-        let mut focus_change = None;
-        if let Some(last_event) = self.last_event.get(&event.source) {
-            let focus_change_evt = FocusChange {
-                host: last_event.event.source.clone(),
-                display_name: last_event.event.display_name.clone(),
-                timestamp: now,
-                duration: (now - last_event.time).num_seconds() as u32,
-            };
+    /// Compare a password against the configured hash while enforcing the delay
+    /// between failed attempts. A legacy hash is upgraded on a successful match.
+    fn check_password(&mut self, password: &str, cur_hash: &str) -> fdo::Result<bool> {
+        let now = self.clock.now();
+        if let Some(secs) = self.auth.backoff.remaining(now) {
+            return Err(fdo::Error::LimitsExceeded(
+                format!("Too many failed attempts. Retry in {} seconds.", secs)
+            ));
+        }
 
-            self.pl.insert_focus_change(&focus_change_evt);
+        let valid = match auth::verify_password(password, cur_hash) {
+            Verification::Valid => {
+                self.auth.backoff.record_success();
+                true
+            },
+            Verification::ValidLegacy => {
+                info!("[PASSWORD] Upgrading the legacy password hash.");
+                self.pl.set_password_hash(&auth::hash_password(password));
+                self.auth.backoff.record_success();
+                true
+            },
+            Verification::Invalid => {
+                warn!("[PASSWORD] Rejected an incorrect password.");
+                self.auth.backoff.record_failure(now);
+                false
+            },
+        };
+        self.pl.set_auth_state(&self.auth);
+        Ok(valid)
+    }
 
-            let host_map = self.timer_durations.entry(last_event.event.source.clone()).or_default();
-            let cur_duration = host_map.entry(last_event.event.display_name.clone()).or_default();
-            *cur_duration += focus_change_evt.duration;
-            
-            debug!("[EVENT]: {} seconds of activity on '{}' to total {} seconds.", focus_change_evt.duration, last_event.event.display_name, cur_duration);
+    /// Require the configured password, failing with `AccessDenied` on a mismatch.
+    fn require_password(&mut self, password: &str) -> fdo::Result<()> {
+        let Some(cur_hash) = self.pl.get_cur_password() else {
+            return Err(fdo::Error::Failed("No password is configured.".to_string()));
+        };
 
-            focus_change = Some(focus_change_evt);
+        if self.check_password(password, &cur_hash)? {
+            Ok(())
+        } else {
+            Err(fdo::Error::AccessDenied("Incorrect password.".to_string()))
         }
+    }
 
-        if set_last_event {
-            let last_event = LastEvent {
-                time: now,
-                event: event.clone(),
-            };
+    /// Authenticate an unlock with either the password or, in partner mode, an
+    /// unused partner code. Both share the delay between failed attempts.
+    fn authenticate(&mut self, credential: &str) -> fdo::Result<UnlockMethod> {
+        let Some(cur_hash) = self.pl.get_cur_password() else {
+            return Err(fdo::Error::Failed("No password is configured.".to_string()));
+        };
 
-            debug!("[EVENT]: Last event from {} at {}.", event.display_name, now.format("%Y-%m-%d %H:%M:%S"));
-            self.last_event.insert(event.source.clone(), last_event);
-        } else {
-            let _ = self.last_event.remove(&event.source);
+        if self.check_password(credential, &cur_hash)? {
+            return Ok(UnlockMethod::Password);
         }
 
-        focus_change
+        let now = self.clock.now().timestamp() as u64;
+        let partner_step = match self.pl.get_partner_secret() {
+            Some(secret) => auth::verify_partner_code(&secret, credential, now).map_err(|e| {
+                warn!("[PARTNER] {}", e);
+                fdo::Error::Failed(e)
+            })?,
+            None => None,
+        };
+        if let Some(step) = partner_step {
+            if self.auth.last_partner_step.is_some_and(|last| step <= last) {
+                warn!("[PARTNER] Rejected a partner code that was already used.");
+                return Err(fdo::Error::AccessDenied("The unlock code was already used.".to_string()));
+            }
+
+            self.auth.backoff.record_success();
+            self.auth.last_partner_step = Some(step);
+            self.pl.set_auth_state(&self.auth);
+            return Ok(UnlockMethod::PartnerCode);
+        }
+
+        Err(fdo::Error::AccessDenied("Incorrect password or unlock code.".to_string()))
     }
-}
 
-#[interface(name = "com.activity_warden.DaemonContext")]
-impl DaemonContext {
-    #[zbus(signal)]
-    async fn duration_changed(signal_emitter: &SignalEmitter<'_>, change: FocusChange) -> Result<()>;
+    /// Reject a mutating call while the daemon is locked, unless the
+    /// caller holds an unexpired edit session.
+    fn ensure_can_edit(&mut self, header: &Header<'_>) -> fdo::Result<()> {
+        if !self.pl.get_lock_state() {
+            return Ok(());
+        }
 
-    pub fn get_daemon_snapshot(&self) -> DaemonSnapshot {
-        DaemonSnapshot { 
-            timers: (*self.timers.load_full()).clone(),
-            durations: self.pl.select_current_durations(),
+        let now = self.clock.now();
+        self.edit_sessions.retain(|_, expiry| *expiry > now);
+        let has_session = header.sender()
+            .is_some_and(|sender| self.edit_sessions.contains_key(sender.as_str()));
+        if has_session {
+            Ok(())
+        } else {
+            warn!("[LOCK] Rejected a mutating call from {:?}.", header.sender());
+            Err(fdo::Error::AccessDenied("The daemon is locked.".to_string()))
         }
     }
 
-    pub fn is_locked(&self) -> bool {
-        self.pl.get_cur_password().is_some()
+    /// The delay applied to changes that loosen the restrictions, if enabled.
+    fn cooling_off(&self) -> Option<Duration> {
+        match self.config.lock.cooling_off_minutes {
+            0 => None,
+            minutes => Some(Duration::minutes(minutes as i64)),
+        }
     }
 
-    /// This function assumes that any password submission is
-    /// intended to either lock or unlock the application.
-    /// First, check if the password matches the existing password.
-    /// If there is no existing password, set the new password.
-    /// 
-    /// False indicates that the application should be locked.
-    /// True indicates that the application should be unlocked.
-    pub fn process_password_submission(&self, password: String) -> bool {
-        if let Some(cur_hash) = &self.pl.get_cur_password() {
-            let mut hasher = Sha256::new();
-            hasher.update(password);
-        
-            let digest = hasher.finalize();
-            let password_hash = hex::encode(digest);
-            let is_correct = *cur_hash == password_hash;
-            if is_correct {
-                self.pl.remove_password();
+    /// Hold back a change for the cooling-off period. Returns the delay in seconds.
+    fn queue_change(&self, change: Change, delay: Duration) -> u32 {
+        let now = self.clock.now();
+        let id = self.pl.insert_pending_change(&change, now, now + delay);
+        info!("[PENDING] Queued the change {} for {} minutes.", id, delay.num_minutes());
+        delay.num_seconds() as u32
+    }
+
+    /// Drop the pending changes to a timer, since a newer change supersedes them.
+    /// Returns whether any change was dropped.
+    fn discard_pending_timer_changes(&self, timer: &Timer) -> bool {
+        self.discard_pending(|c| c.timer().is_some_and(|t| t.display_name == timer.display_name && t.host == timer.host))
+    }
+
+    /// Drop the pending changes that `is_superseded` selects. Returns whether any existed.
+    fn discard_pending(&self, is_superseded: impl Fn(&Change) -> bool) -> bool {
+        let mut discarded = false;
+        for p in self.pl.select_pending_changes() {
+            if is_superseded(&p.change) {
+                discarded |= self.pl.delete_pending_change(p.id);
             }
+        }
+        discarded
+    }
 
-            return is_correct;
-        } else {
-            self.pl.set_new_password(password);
-            return false;
+    /// Whether a change that `is_requested` selects is already pending.
+    fn pending_change(&self, is_requested: impl Fn(&Change) -> bool) -> bool {
+        self.pl.select_pending_changes().iter().any(|p| is_requested(&p.change))
+    }
+
+    /// Drop a pending unlock. Returns whether one existed.
+    fn discard_pending_unlock(&self) -> bool {
+        self.discard_pending(|c| matches!(c, Change::Unlock { .. }))
+    }
+
+    /// Let the clients know that the configuration changed, along with the day start it sets
+    /// and, if `pending_changed` is set, the pending changes.
+    pub async fn announce_config(&self, emitter: &SignalEmitter<'_>, pending_changed: bool) -> Result<()> {
+        Self::config_changed(emitter).await?;
+        self.day_start_hour_changed(emitter).await?;
+        if pending_changed {
+            Self::pending_changes_changed(emitter).await?;
         }
+        Ok(())
     }
 
-    pub fn insert_timer(&self, timer: Timer) {
+    /// Apply the pending changes whose cooling-off period has elapsed.
+    pub async fn apply_due_changes(&mut self, emitter: &SignalEmitter<'_>) -> Result<()> {
+        let now = self.clock.now();
+        let due = self.pl.select_pending_changes()
+            .into_iter()
+            .filter(|p| p.apply_at <= now)
+            .collect::<Vec<PendingChange>>();
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        for p in due {
+            info!("[PENDING] Applying the change {} requested at {}.", p.id, p.requested_at);
+            self.pl.delete_pending_change(p.id);
+            match p.change {
+                Change::Unlock { method, reason } => {
+                    if self.pl.get_lock_state() {
+                        self.complete_unlock(method, reason);
+                        Self::lock_state_changed(emitter, false).await?;
+                    }
+                },
+                Change::UpdateTimer(timer) => {
+                    self.replace_timer(timer);
+                    self.active_sessions_changed(emitter).await?;
+                },
+                Change::DeleteTimer(timer) => {
+                    self.remove_timer(&timer);
+                    self.active_sessions_changed(emitter).await?;
+                },
+                Change::CoolingOff { minutes } => {
+                    info!("[CONFIG] The cooling-off period is now {} minutes.", minutes);
+                    self.config.lock.cooling_off_minutes = minutes;
+                    self.store_settings_in_force();
+                    Self::config_changed(emitter).await?;
+                },
+                Change::DayStart { hour } => {
+                    self.config.tracking.day_start_hour = hour;
+                    self.store_settings_in_force();
+                    self.follow_day_start();
+                    self.announce_config(emitter, false).await?;
+                    self.active_sessions_changed(emitter).await?;
+                },
+            }
+        }
+
+        Self::pending_changes_changed(emitter).await
+    }
+
+    /// Unlock the daemon and record the unlock for review.
+    fn complete_unlock(&mut self, method: UnlockMethod, reason: String) {
+        info!("[LOCK] Unlocking the daemon ({}).", method);
+        self.pl.insert_unlock_event(&UnlockEvent {
+            timestamp: self.clock.now(),
+            method,
+            reason,
+        });
+        self.pl.set_lock_state(false);
+        self.edit_sessions.clear();
+    }
+
+    pub fn add_timer(&self, timer: Timer) {
         self.timers.rcu(|old| {
             let mut old_timers = (**old).clone();
             old_timers.push(timer.clone());
@@ -175,27 +657,45 @@ impl DaemonContext {
         self.pl.modify_timer(QueryType::INSERT, timer);
     }
 
-    pub fn delete_timer(&self, timer: Timer) {
+    /// Reject a timer that cannot be added, before any state changes.
+    pub fn check_new_timer(&self, timer: &Timer) -> fdo::Result<()> {
+        if timer.allowed_days.len() != 7 {
+            return Err(fdo::Error::InvalidArgs("A timer must specify 7 days.".to_string()));
+        }
+        let exists = self.timers.load().iter()
+            .any(|t| t.display_name == timer.display_name && t.host == timer.host);
+        if exists {
+            return Err(fdo::Error::Failed(
+                format!("A timer for '{}' on {} already exists.", timer.display_name, timer.host)
+            ));
+        }
+        Ok(())
+    }
+
+    fn remove_timer(&self, timer: &Timer) {
+        let is_same = |t: &Timer| t.display_name == timer.display_name && t.host == timer.host;
+        let Some(stored) = self.timers.load().iter().find(|t| is_same(t)).cloned() else {
+            return;
+        };
+
         self.timers.rcu(|old| {
             let old_timers = (**old).clone();
             old_timers.into_iter()
-                .filter(|t| {
-                    t.display_name != timer.display_name || t.host != timer.host
-                })
+                .filter(|t| !is_same(t))
                 .collect::<Vec<Timer>>()
         });
-        self.pl.modify_timer(QueryType::DELETE, timer);
+        self.pl.modify_timer(QueryType::DELETE, stored);
     }
 
-    pub fn update_timer(&self, timer: Timer) {
+    fn replace_timer(&self, timer: Timer) {
         self.timers.rcu(|old| {
             let old_timers = (**old).clone();
             old_timers.into_iter()
                 .map(|t| {
                     if t.display_name == timer.display_name && t.host == timer.host {
-                        return timer.clone();
+                        timer.clone()
                     } else {
-                        return t;
+                        t
                     }
                 })
                 .collect::<Vec<Timer>>()
@@ -203,4 +703,645 @@ impl DaemonContext {
 
         self.pl.modify_timer(QueryType::UPDATE, timer);
     }
-}
\ No newline at end of file
+
+    /// Update the corresponding durations for a given event.
+    pub fn update_event_durations(&mut self, event: &Event, set_last_event: bool) -> Option<FocusChange> {
+        let now = self.clock.now();
+        let focus_change = self.last_event.get(&event.source)
+            .cloned()
+            .map(|last_event| self.complete_session(&last_event, now));
+
+        if set_last_event {
+            let stamp = self.clock.stamp();
+            let last_event = LastEvent {
+                time: now,
+                event: event.clone(),
+                focused_at: stamp,
+                counted: 0,
+                counting_since: Some(stamp),
+            };
+
+            debug!("[EVENT]: Last event from {} at {}.", event.display_name, now.format("%Y-%m-%d %H:%M:%S"));
+            self.last_event.insert(event.source.clone(), last_event);
+        } else {
+            let _ = self.last_event.remove(&event.source);
+        }
+
+        focus_change
+    }
+
+    /// Record the counted time of a session and add it to the durations of the day.
+    fn complete_session(&mut self, last_event: &LastEvent, now: DateTime<Utc>) -> FocusChange {
+        let focus_change = FocusChange {
+            host: last_event.event.source.clone(),
+            display_name: last_event.event.display_name.clone(),
+            timestamp: now,
+            duration: self.session_seconds(last_event),
+        };
+
+        self.pl.insert_focus_change(&focus_change);
+        self.pl.delete_active_session(&focus_change.host);
+
+        let host_map = self.timer_durations.entry(last_event.event.source.clone()).or_default();
+        let cur_duration = host_map.entry(last_event.event.display_name.clone()).or_default();
+        *cur_duration += focus_change.duration;
+
+        debug!("[EVENT]: {} seconds of activity on '{}' to total {} seconds.", focus_change.duration, last_event.event.display_name, cur_duration);
+        focus_change
+    }
+
+    /// The seconds that a session in progress counted so far.
+    pub fn session_seconds(&self, last_event: &LastEvent) -> u32 {
+        last_event.counted + last_event.counting_since
+            .map(|stamp| self.clock.seconds_since(stamp))
+            .unwrap_or(0)
+    }
+
+    /// The seconds counted today on a display name of a host, including the session in progress.
+    pub fn seconds_today(&self, host: &Host, display_name: &str) -> u32 {
+        let recorded = self.timer_durations.get(host)
+            .and_then(|host_durations| host_durations.get(display_name))
+            .copied()
+            .unwrap_or(0);
+        let current = self.last_event.get(host)
+            .filter(|last_event| last_event.event.display_name == display_name)
+            .map(|last_event| self.session_seconds(last_event))
+            .unwrap_or(0);
+        recorded + current
+    }
+
+    /// Whether the session of a host currently counts under the aggregation policy.
+    pub fn is_counting(&self, host: &Host) -> bool {
+        self.last_event.get(host).is_some_and(|last_event| last_event.counting_since.is_some())
+    }
+
+    /// Pause and resume the sessions in progress according to the aggregation policy.
+    /// Returns the hosts whose sessions changed between counting and paused.
+    pub fn resolve_aggregation(&mut self) -> Vec<Host> {
+        let sessions = self.last_event.iter()
+            .map(|(host, last_event)| (host.clone(), last_event.focused_at))
+            .collect::<Vec<_>>();
+        let counting = aggregation::counting_hosts(self.config.tracking.aggregation, &sessions);
+
+        let stamp = self.clock.stamp();
+        let mut changed = Vec::new();
+        for (host, last_event) in self.last_event.iter_mut() {
+            let should_count = counting.contains(host);
+            match (last_event.counting_since, should_count) {
+                (Some(since), false) => {
+                    debug!("[AGGREGATION] Pausing the session of '{}' on {}.", last_event.event.display_name, host);
+                    last_event.counted += self.clock.seconds_since(since);
+                    last_event.counting_since = None;
+                },
+                (None, true) => {
+                    debug!("[AGGREGATION] Resuming the session of '{}' on {}.", last_event.event.display_name, host);
+                    last_event.counting_since = Some(stamp);
+                },
+                _ => continue,
+            }
+            changed.push(host.clone());
+        }
+
+        changed.sort();
+        changed
+    }
+
+    /// Store the time of the sessions in progress, so that it survives a crash of the daemon.
+    pub fn checkpoint_sessions(&self) {
+        let now = self.clock.now();
+        let sessions = self.last_event.values()
+            .map(|last_event| FocusChange {
+                host: last_event.event.source.clone(),
+                display_name: last_event.event.display_name.clone(),
+                timestamp: now,
+                duration: self.session_seconds(last_event),
+            })
+            .collect::<Vec<FocusChange>>();
+        self.pl.checkpoint_active_sessions(&sessions);
+    }
+
+    /// Record the sessions in progress and start them afresh.
+    /// The focus order and paused sessions are kept.
+    pub fn split_sessions(&mut self) {
+        self.split_sessions_at(self.clock.now());
+    }
+
+    /// Record the share of the sessions in progress before `boundary`, i.e. the start of
+    /// a day, and carry the time counted since into the sessions that continue after it.
+    fn split_sessions_at(&mut self, boundary: DateTime<Utc>) {
+        let now = self.clock.now();
+        let stamp = self.clock.stamp();
+        let since_boundary = (now - boundary).num_seconds().max(0) as u32;
+        for (host, last_event) in self.last_event.clone() {
+            // Only the time counted without a pause can be placed on either side of the boundary.
+            let after = last_event.counting_since
+                .map(|since| self.clock.seconds_since(since).min(since_boundary))
+                .unwrap_or(0);
+            let before = LastEvent {
+                counted: self.session_seconds(&last_event) - after,
+                counting_since: None,
+                ..last_event.clone()
+            };
+            // A session ends within the day of its timestamp, so the share ends just before the boundary.
+            self.complete_session(&before, (boundary - Duration::seconds(1)).max(last_event.time));
+            self.last_event.insert(host, LastEvent {
+                time: boundary.max(last_event.time),
+                counted: after,
+                counting_since: last_event.counting_since.map(|_| stamp),
+                ..last_event
+            });
+        }
+    }
+}
+
+#[interface(name = "com.activity_warden.DaemonContext")]
+impl DaemonContext {
+    #[zbus(signal)]
+    async fn duration_changed(signal_emitter: &SignalEmitter<'_>, change: FocusChange) -> Result<()>;
+
+    pub fn get_daemon_snapshot(&self) -> DaemonSnapshot {
+        DaemonSnapshot { 
+            timers: (*self.timers.load_full()).clone(),
+            durations: self.pl.select_current_durations(self.today(), self.config.tracking.day_start_hour),
+        }
+    }
+
+    /// Retrieve every session that ended within `[start, end)`, ordered by time.
+    /// For large ranges, prefer `get_sessions_page`.
+    pub fn get_sessions(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<Vec<FocusChange>> {
+        if start >= end {
+            return Err(fdo::Error::InvalidArgs("The start of the range must precede the end.".to_string()));
+        }
+
+        Ok(self.pl.select_sessions(start, end, -1, 0))
+    }
+
+    /// Retrieve at most `limit` sessions within `[start, end)`, skipping the first `offset`.
+    /// The page also reports the total number of sessions in the range.
+    pub fn get_sessions_page(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        offset: u32,
+        limit: u32,
+    ) -> fdo::Result<SessionPage> {
+        if start >= end {
+            return Err(fdo::Error::InvalidArgs("The start of the range must precede the end.".to_string()));
+        }
+        if limit == 0 || limit > MAX_SESSION_PAGE_SIZE {
+            return Err(fdo::Error::InvalidArgs(format!("The page size must be within 1 and {}.", MAX_SESSION_PAGE_SIZE)));
+        }
+
+        Ok(SessionPage {
+            sessions: self.pl.select_sessions(start, end, limit as i64, offset as i64),
+            total: self.pl.count_sessions(start, end),
+        })
+    }
+
+    /// Sum the time spent per display name within `[start, end)`, including
+    /// days that have already been rolled up into daily summaries.
+    pub fn get_durations(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<DurationMap> {
+        if start >= end {
+            return Err(fdo::Error::InvalidArgs("The start of the range must precede the end.".to_string()));
+        }
+
+        Ok(self.pl.select_durations(start, end, self.config.tracking.day_start_hour))
+    }
+
+    /// Export all timers, sessions and settings. See `backup` for the formats.
+    pub fn export_data(&self, format: BackupFormat) -> fdo::Result<String> {
+        backup::export_data(&self.pl, format).map_err(fdo::Error::Failed)
+    }
+
+    /// Import previously exported data. Records that already exist are kept as-is.
+    /// The timers and today's durations are read again, since the import may extend both.
+    pub async fn import_data(
+        &mut self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        format: BackupFormat,
+        data: String,
+    ) -> fdo::Result<ImportSummary> {
+        self.ensure_can_edit(&header)?;
+        let summary = backup::import_data(&self.pl, format, &data, self.config.tracking.day_start_hour)
+            .map_err(fdo::Error::InvalidArgs)?;
+
+        if summary.timers_imported > 0 {
+            self.timers.store(Arc::new(self.pl.select_timers()));
+        }
+        if summary.timers_imported > 0 || summary.focus_changes_imported > 0 || summary.daily_summaries_imported > 0 {
+            self.timer_durations = self.pl.select_current_durations(self.today(), self.config.tracking.day_start_hour);
+            self.active_sessions_changed(&emitter).await?;
+        }
+
+        Ok(summary)
+    }
+
+    /// Report the gaps in tracking within `[start, end)` along with the current
+    /// state of the watchers.
+    pub fn get_coverage(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<Coverage> {
+        if start >= end {
+            return Err(fdo::Error::InvalidArgs("The start of the range must precede the end.".to_string()));
+        }
+
+        let now = self.clock.now();
+        let gaps = self.pl.select_coverage_gaps(start, end, now);
+        let mut watchers = self.watchers.iter()
+            .map(|(host, state)| WatcherStatus {
+                host: host.clone(),
+                connected: state.connected && state.open_gap.is_none(),
+                last_seen: state.last_seen,
+            })
+            .collect::<Vec<WatcherStatus>>();
+        watchers.sort_by(|a, b| a.host.cmp(&b.host));
+
+        Ok(Coverage {
+            start,
+            end,
+            uncovered: coverage::uncovered_seconds(&gaps, start, end.min(now)),
+            gaps,
+            watchers,
+        })
+    }
+
+    /// Retrieve the sessions currently in progress along with their remaining time.
+    pub fn get_status(&self) -> Vec<ActiveSession> {
+        let mut sessions = self.last_event.values()
+            .map(|last_event| {
+                let host = last_event.event.source.clone();
+                let display_name = last_event.event.display_name.clone();
+                let duration = self.seconds_today(&host, &display_name);
+                let limit = self.time_limit_today(&display_name);
+
+                ActiveSession {
+                    host: host.clone(),
+                    display_name,
+                    started_at: last_event.time,
+                    duration,
+                    has_timer: limit.is_some(),
+                    remaining: limit.unwrap_or(0).saturating_sub(duration),
+                    counting: self.is_counting(&host),
+                }
+            })
+            .collect::<Vec<ActiveSession>>();
+
+        sessions.sort_by(|a, b| a.host.cmp(&b.host));
+        sessions
+    }
+
+    /// The sessions in progress, as returned by `GetStatus`. A change is announced whenever
+    /// a session starts, ends or pauses, or a timer changes, but not as time passes, so
+    /// clients count `remaining` down themselves while a session is counting.
+    #[zbus(property)]
+    pub fn active_sessions(&self) -> Vec<ActiveSession> {
+        self.get_status()
+    }
+
+    /// The local hour at which a tracking day starts, i.e. 4 if the time spent before
+    /// 4 AM counts towards the previous day.
+    #[zbus(property)]
+    pub fn day_start_hour(&self) -> u32 {
+        self.config.tracking.day_start_hour
+    }
+
+    #[zbus(signal)]
+    pub async fn config_changed(signal_emitter: &SignalEmitter<'_>) -> Result<()>;
+
+    /// The contents of the configuration file, or the defaults if there is none.
+    pub fn get_config(&self) -> fdo::Result<String> {
+        DaemonConfig::contents().map_err(fdo::Error::Failed)
+    }
+
+    /// Read the configuration file again, as on `SIGHUP`. Since the file may have
+    /// been edited, this requires the daemon to be unlocked or an edit session.
+    pub async fn reload_config(
+        &mut self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+    ) -> fdo::Result<()> {
+        self.ensure_can_edit(&header)?;
+        let pending_changed = self.reload_config_file(true).map_err(fdo::Error::Failed)?;
+        self.announce_config(&emitter, pending_changed).await?;
+        Ok(())
+    }
+
+    /// Replace the configuration file with `contents` and apply it. Like the timers,
+    /// the configuration can only be edited while unlocked or within an edit session.
+    pub async fn update_config(
+        &mut self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        contents: String,
+    ) -> fdo::Result<()> {
+        self.ensure_can_edit(&header)?;
+        let config = DaemonConfig::write(&contents).map_err(fdo::Error::InvalidArgs)?;
+        info!("[CONFIG] The configuration file was replaced.");
+        let pending_changed = self.apply_config(config, true);
+        self.announce_config(&emitter, pending_changed).await?;
+        Ok(())
+    }
+
+    /// Grant the calling connection the right to modify timers for a limited
+    /// time while the daemon stays locked. Returns the session length in seconds.
+    pub fn begin_edit_session(&mut self, #[zbus(header)] header: Header<'_>, password: String) -> fdo::Result<u32> {
+        if !self.pl.get_lock_state() {
+            return Err(fdo::Error::Failed("The daemon is not locked.".to_string()));
+        }
+        let Some(sender) = header.sender() else {
+            return Err(fdo::Error::AccessDenied("The caller has no unique name.".to_string()));
+        };
+
+        self.require_password(&password)?;
+
+        info!("[LOCK] Granting {} an edit session.", sender);
+        let expiry = self.clock.now() + Duration::seconds(EDIT_SESSION_SECS);
+        self.edit_sessions.insert(sender.to_string(), expiry);
+        Ok(EDIT_SESSION_SECS as u32)
+    }
+
+    /// Revoke the edit session of the calling connection, if any.
+    pub fn end_edit_session(&mut self, #[zbus(header)] header: Header<'_>) {
+        if let Some(sender) = header.sender() {
+            self.edit_sessions.remove(sender.as_str());
+        }
+    }
+
+    #[zbus(signal)]
+    async fn lock_state_changed(signal_emitter: &SignalEmitter<'_>, is_locked: bool) -> Result<()>;
+
+    pub fn is_locked(&self) -> bool {
+        self.pl.get_lock_state()
+    }
+
+    pub fn has_password(&self) -> bool {
+        self.pl.get_cur_password().is_some()
+    }
+
+    /// Lock the daemon. A password must be configured beforehand,
+    /// otherwise the lock could never be released.
+    pub async fn lock(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<()> {
+        if self.pl.get_cur_password().is_none() {
+            return Err(fdo::Error::Failed("Set a password before locking.".to_string()));
+        }
+
+        if !self.pl.get_lock_state() {
+            info!("[LOCK] Locking the daemon.");
+            self.pl.set_lock_state(true);
+            if self.discard_pending_unlock() {
+                Self::pending_changes_changed(&emitter).await?;
+            }
+            Self::lock_state_changed(&emitter, true).await?;
+        }
+        Ok(())
+    }
+
+    /// Unlock the daemon with the configured password or, in partner mode, a
+    /// partner code. Every unlock is logged along with its reason, which is
+    /// mandatory in partner mode. Repeated failures are delayed with a `LimitsExceeded` error.
+    /// While a cooling-off period is configured, the unlock is queued instead.
+    /// Returns the seconds until the unlock applies.
+    pub async fn unlock(
+        &mut self,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        credential: String,
+        reason: String,
+    ) -> fdo::Result<u32> {
+        if !self.pl.get_lock_state() {
+            return Ok(0);
+        }
+
+        let reason = reason.trim().to_string();
+        if reason.is_empty() && self.pl.get_partner_secret().is_some() {
+            return Err(fdo::Error::InvalidArgs("A reason is required while partner mode is enabled.".to_string()));
+        }
+
+        // A repeated request keeps the original schedule rather than restarting it.
+        if let Some(pending) = self.pl.select_pending_changes()
+            .into_iter()
+            .find(|p| matches!(p.change, Change::Unlock { .. })) {
+            return Ok((pending.apply_at - self.clock.now()).num_seconds().max(0) as u32);
+        }
+
+        let method = self.authenticate(&credential)?;
+        if let Some(delay) = self.cooling_off() {
+            let secs = self.queue_change(Change::Unlock { method, reason }, delay);
+            Self::pending_changes_changed(&emitter).await?;
+            return Ok(secs);
+        }
+
+        self.complete_unlock(method, reason);
+        Self::lock_state_changed(&emitter, false).await?;
+        Ok(0)
+    }
+
+    /// Replace the configured password. The current password is required
+    /// if one exists. The lock state is left unchanged.
+    pub fn change_password(&mut self, old_password: String, new_password: String) -> fdo::Result<()> {
+        if new_password.is_empty() {
+            return Err(fdo::Error::InvalidArgs("The new password must not be empty.".to_string()));
+        }
+        if self.pl.get_cur_password().is_some() {
+            self.require_password(&old_password)?;
+        }
+
+        info!("[PASSWORD] Changing the password.");
+        self.pl.set_password_hash(&auth::hash_password(&new_password));
+        Ok(())
+    }
+
+    /// Remove the configured password, which also unlocks the daemon. While a
+    /// cooling-off period is configured, the daemon must be unlocked beforehand.
+    pub async fn clear_password(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>, password: String) -> fdo::Result<()> {
+        if self.cooling_off().is_some() && self.pl.get_lock_state() {
+            return Err(fdo::Error::Failed("Unlock the daemon before removing the password.".to_string()));
+        }
+        self.require_password(&password)?;
+
+        info!("[PASSWORD] Clearing the password.");
+        let was_locked = self.pl.get_lock_state();
+        self.pl.remove_password();
+        if was_locked {
+            self.pl.insert_unlock_event(&UnlockEvent {
+                timestamp: self.clock.now(),
+                method: UnlockMethod::Password,
+                reason: "The password was removed.".to_string(),
+            });
+            self.pl.set_lock_state(false);
+            self.edit_sessions.clear();
+            Self::lock_state_changed(&emitter, false).await?;
+        }
+        Ok(())
+    }
+
+    pub fn has_partner_mode(&self) -> bool {
+        self.pl.get_partner_secret().is_some()
+    }
+
+    /// Generate a new secret to share with an accountability partner, replacing any
+    /// previous one. The partner's codes are then accepted by `unlock` in place of the
+    /// password. Returns the `otpauth://` URL of the secret for an authenticator app.
+    pub fn enable_partner_mode(&mut self, password: String) -> fdo::Result<String> {
+        self.require_password(&password)?;
+
+        info!("[PARTNER] Enabling partner mode with a new secret.");
+        let secret = auth::generate_partner_secret();
+        self.pl.set_partner_secret(&secret);
+        self.auth.last_partner_step = None;
+        self.pl.set_auth_state(&self.auth);
+        auth::partner_url(&secret).map_err(fdo::Error::Failed)
+    }
+
+    /// Stop accepting partner codes. Only the password may disable partner mode.
+    pub fn disable_partner_mode(&mut self, password: String) -> fdo::Result<()> {
+        self.require_password(&password)?;
+
+        if self.pl.get_partner_secret().is_some() {
+            info!("[PARTNER] Disabling partner mode.");
+            self.pl.remove_partner_secret();
+        }
+        Ok(())
+    }
+
+    /// Retrieve the unlocks within `[start, end)`, ordered by time.
+    pub fn get_unlock_events(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> fdo::Result<Vec<UnlockEvent>> {
+        if start >= end {
+            return Err(fdo::Error::InvalidArgs("The start of the range must precede the end.".to_string()));
+        }
+
+        Ok(self.pl.select_unlock_events(start, end))
+    }
+
+    /// Add a timer for a display name of a host that has none yet.
+    pub async fn insert_timer(&self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>, timer: Timer) -> fdo::Result<()> {
+        self.check_new_timer(&timer)?;
+        self.add_timer(timer);
+        self.active_sessions_changed(&emitter).await?;
+        Ok(())
+    }
+
+    /// Delete a timer. While a cooling-off period is configured, the deletion is
+    /// queued instead. Returns the seconds until the deletion applies.
+    pub async fn delete_timer(
+        &mut self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        timer: Timer,
+    ) -> fdo::Result<u32> {
+        self.ensure_can_edit(&header)?;
+        self.discard_pending_timer_changes(&timer);
+        if let Some(delay) = self.cooling_off() {
+            let secs = self.queue_change(Change::DeleteTimer(timer), delay);
+            Self::pending_changes_changed(&emitter).await?;
+            return Ok(secs);
+        }
+
+        self.remove_timer(&timer);
+        self.active_sessions_changed(&emitter).await?;
+        Ok(0)
+    }
+
+    /// Update the limit or the days of a timer. While a cooling-off period is configured,
+    /// an update that allows more time is queued instead, whereas tightening a timer
+    /// always applies immediately. Returns the seconds until the update applies.
+    pub async fn update_timer(
+        &mut self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_emitter)] emitter: SignalEmitter<'_>,
+        timer: Timer,
+    ) -> fdo::Result<u32> {
+        self.ensure_can_edit(&header)?;
+        if timer.allowed_days.len() != 7 {
+            return Err(fdo::Error::InvalidArgs("A timer must specify 7 days.".to_string()));
+        }
+
+        let loosens = self.timers.load().iter()
+            .find(|t| t.display_name == timer.display_name && t.host == timer.host)
+            .is_some_and(|cur| pending::is_loosening(cur, &timer));
+        let had_pending = self.discard_pending_timer_changes(&timer);
+        if loosens && let Some(delay) = self.cooling_off() {
+            let secs = self.queue_change(Change::UpdateTimer(timer), delay);
+            Self::pending_changes_changed(&emitter).await?;
+            return Ok(secs);
+        }
+
+        self.replace_timer(timer);
+        self.active_sessions_changed(&emitter).await?;
+        if had_pending {
+            Self::pending_changes_changed(&emitter).await?;
+        }
+        Ok(0)
+    }
+
+    #[zbus(signal)]
+    async fn pending_changes_changed(signal_emitter: &SignalEmitter<'_>) -> Result<()>;
+
+    pub fn get_pending_changes(&self) -> PendingChanges {
+        let pending = self.pl.select_pending_changes();
+        let unlock_at = pending.iter()
+            .find(|p| matches!(p.change, Change::Unlock { .. }))
+            .map(|p| p.apply_at);
+        let cooling_off = pending.iter()
+            .find_map(|p| match p.change {
+                Change::CoolingOff { minutes } => Some((minutes, p.apply_at)),
+                _ => None,
+            });
+        let day_start = pending.iter()
+            .find_map(|p| match p.change {
+                Change::DayStart { hour } => Some((hour, p.apply_at)),
+                _ => None,
+            });
+        let timer_changes = pending.into_iter()
+            .filter_map(|p| {
+                let (action, timer) = match p.change {
+                    Change::Unlock { .. } | Change::CoolingOff { .. } | Change::DayStart { .. } => return None,
+                    Change::UpdateTimer(timer) => (TimerAction::Update, timer),
+                    Change::DeleteTimer(timer) => (TimerAction::Delete, timer),
+                };
+
+                Some(PendingTimerChange {
+                    id: p.id,
+                    action,
+                    timer,
+                    requested_at: p.requested_at,
+                    apply_at: p.apply_at,
+                })
+            })
+            .collect();
+
+        PendingChanges {
+            unlock_pending: unlock_at.is_some(),
+            unlock_at: unlock_at.unwrap_or_default(),
+            timer_changes,
+            cooling_off_pending: cooling_off.is_some(),
+            cooling_off_minutes: cooling_off.map(|(minutes, _)| minutes).unwrap_or_default(),
+            cooling_off_at: cooling_off.map(|(_, apply_at)| apply_at).unwrap_or_default(),
+            day_start_pending: day_start.is_some(),
+            day_start_hour: day_start.map(|(hour, _)| hour).unwrap_or_default(),
+            day_start_at: day_start.map(|(_, apply_at)| apply_at).unwrap_or_default(),
+        }
+    }
+
+    /// Withdraw a pending unlock, if any.
+    pub async fn cancel_unlock(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>) -> fdo::Result<()> {
+        if self.discard_pending_unlock() {
+            info!("[PENDING] Cancelled the pending unlock.");
+            Self::pending_changes_changed(&emitter).await?;
+        }
+        Ok(())
+    }
+
+    /// Withdraw a pending timer change.
+    pub async fn cancel_timer_change(&mut self, #[zbus(signal_emitter)] emitter: SignalEmitter<'_>, id: i64) -> fdo::Result<()> {
+        let is_timer_change = self.pl.select_pending_changes()
+            .iter()
+            .any(|p| p.id == id && p.change.timer().is_some());
+        if !is_timer_change {
+            return Err(fdo::Error::InvalidArgs(format!("No pending timer change has the id {}.", id)));
+        }
+
+        info!("[PENDING] Cancelled the pending change {}.", id);
+        self.pl.delete_pending_change(id);
+        Self::pending_changes_changed(&emitter).await?;
+        Ok(())
+    }
+}
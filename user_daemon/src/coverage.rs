@@ -0,0 +1,46 @@
+use chrono::{DateTime, Duration, Utc};
+
+use shared::types::daemon::CoverageGap;
+use shared::types::event::HEARTBEAT_INTERVAL_SECS;
+
+/// A watcher that misses this many seconds of heartbeats is considered disconnected.
+/// The same threshold applies to the daemon itself between two runs.
+pub const LIVENESS_TIMEOUT_SECS: i64 = 3 * HEARTBEAT_INTERVAL_SECS as i64;
+
+#[derive(Clone, Debug)]
+pub struct WatcherState {
+    /// The moment of the last event or heartbeat from the watcher.
+    pub last_seen: DateTime<Utc>,
+    /// Whether heartbeats are expected, i.e. the watcher has not disconnected cleanly.
+    pub connected: bool,
+    /// The gap that was opened when the heartbeats stopped, if any.
+    pub open_gap: Option<i64>,
+}
+
+/// The seconds within `[start, end)` that fall into at least one gap.
+pub fn uncovered_seconds(gaps: &[CoverageGap], start: DateTime<Utc>, end: DateTime<Utc>) -> u32 {
+    let mut intervals = gaps.iter()
+        .map(|g| (g.start.max(start), g.end.min(end)))
+        .filter(|(s, e)| s < e)
+        .collect::<Vec<_>>();
+    intervals.sort();
+
+    // Merge the overlapping intervals so that no second is counted twice.
+    let mut total = Duration::zero();
+    let mut current: Option<(DateTime<Utc>, DateTime<Utc>)> = None;
+    for (s, e) in intervals {
+        match current {
+            Some((cur_s, cur_e)) if s <= cur_e => current = Some((cur_s, cur_e.max(e))),
+            Some((cur_s, cur_e)) => {
+                total += cur_e - cur_s;
+                current = Some((s, e));
+            },
+            None => current = Some((s, e)),
+        }
+    }
+    if let Some((cur_s, cur_e)) = current {
+        total += cur_e - cur_s;
+    }
+
+    total.num_seconds() as u32
+}
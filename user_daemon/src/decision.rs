@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use log::info;
+use std::time::Duration;
+use zbus::Result;
+
+use shared::dbus::Host;
+use shared::types::{day, Event, EventType};
+use shared::types::schema::FocusChange;
+use crate::context::DaemonContext;
+use crate::watcher::Watcher;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayNameAction {
+    /// Close the given display name.
+    Block,
+    /// Set a timer for `u32` seconds.
+    Time(u32),
+    /// Track the time, but perform no actions.
+    Ignore,
+}
+
+/// Determine how much time must elapse until the next day starts, `day_start_hour`
+/// hours after midnight in the local timezone. At this point, the daemon will wake
+/// up and close any stale messages.
+///
+/// This is only necessary to prevent durations from the previous day from
+/// impacting the following day. For example, a user spending 30 minutes on a
+/// display name around the day boundary would want that time properly split
+/// across it.
+pub fn time_until_next_day_start(now: DateTime<Utc>, day_start_hour: u32) -> Duration {
+    let next_day_start = day::next_day_start(now, day_start_hour);
+    Duration::from_secs((next_day_start - now).num_seconds().max(0) as u64)
+}
+
+/// Determine if a particular display name is blocked or if a new timer should be set.
+pub fn is_display_name_blocked(context: &DaemonContext, host: &Host, display_name: &str) -> DisplayNameAction {
+    let Some(time_limit) = context.time_limit_today(display_name) else {
+        // Otherwise, conclude that the name is not blocked.
+        return DisplayNameAction::Ignore;
+    };
+
+    // If the timer is set to 0, or disabled on the current day, no activity will be tracked.
+    // As such, we implicitly know that the page is blocked.
+    if time_limit == 0 {
+        info!("[BLOCKING] {}: no time is allotted today.", display_name);
+        return DisplayNameAction::Block;
+    }
+
+    let cur_duration = context.seconds_today(host, display_name);
+    if cur_duration >= time_limit {
+        info!("[BLOCKING] {}: current duration ({}) is greater than the limit ({}).", display_name, cur_duration, time_limit);
+        DisplayNameAction::Block
+    } else {
+        info!("[NON-BLOCKING] {}: current duration ({}) is less than the limit ({}).", display_name, cur_duration, time_limit);
+        DisplayNameAction::Time(time_limit - cur_duration)
+    }
+}
+
+/// The result of applying an event to the context.
+#[derive(Debug)]
+pub struct Outcome {
+    /// The session that the event completed, if any.
+    pub focus_change: Option<FocusChange>,
+    /// The action for the display name of the event, followed by the actions
+    /// for the hosts whose sessions the aggregation policy paused or resumed.
+    pub actions: Vec<(Event, DisplayNameAction)>,
+}
+
+/// Apply an event from a watcher to the context.
+pub fn process_event(context: &mut DaemonContext, event: &Event) -> Outcome {
+    context.reset_daily_state();
+    context.record_heartbeat(&event.source);
+    let (focus_change, action) = match event.event_type {
+        EventType::FocusChange => {
+            // Complete the previous session first, so that the time spent on the
+            // same display name until now counts towards its limit.
+            let focus_change = context.update_event_durations(event, true);
+            let action = is_display_name_blocked(context, &event.source, &event.display_name);
+            if action == DisplayNameAction::Block {
+                // A blocked display name is closed right away and accrues no time.
+                context.last_event.remove(&event.source);
+            }
+            (focus_change, action)
+        },
+        EventType::FocusLost => (context.update_event_durations(event, false), DisplayNameAction::Ignore),
+        EventType::Disconnect => {
+            context.record_disconnect(&event.source);
+            (context.update_event_durations(event, false), DisplayNameAction::Ignore)
+        },
+        EventType::AFK => { /* Implement in the future. */ (None, DisplayNameAction::Ignore) },
+        EventType::Heartbeat => return Outcome {
+            focus_change: None,
+            actions: vec![(event.clone(), DisplayNameAction::Ignore)],
+        },
+    };
+
+    let changed = context.resolve_aggregation();
+    let mut actions = vec![(event.clone(), pause_if_not_counting(context, &event.source, action))];
+    for host in changed.into_iter().filter(|host| *host != event.source) {
+        let last_event = context.last_event[&host].event.clone();
+        let action = is_display_name_blocked(context, &host, &last_event.display_name);
+        actions.push((last_event, pause_if_not_counting(context, &host, action)));
+    }
+
+    Outcome { focus_change, actions }
+}
+
+/// The action for the display name of every session in progress, i.e. once a new day
+/// started and the sessions continue on its budget.
+pub fn session_actions(context: &DaemonContext) -> Vec<(Event, DisplayNameAction)> {
+    context.last_event.iter()
+        .map(|(host, last_event)| {
+            let action = is_display_name_blocked(context, host, &last_event.event.display_name);
+            (last_event.event.clone(), pause_if_not_counting(context, host, action))
+        })
+        .collect()
+}
+
+/// A session that does not count cannot run out of time, so it needs no timer.
+fn pause_if_not_counting(context: &DaemonContext, host: &Host, action: DisplayNameAction) -> DisplayNameAction {
+    match action {
+        DisplayNameAction::Time(_) if !context.is_counting(host) => DisplayNameAction::Ignore,
+        action => action,
+    }
+}
+
+/// Close the provided display name once `timeout` seconds have passed.
+pub async fn block_display_name<W: Watcher>(watcher: W, event: Event, timeout: u32) -> Result<()> {
+    // Wait for the timer to expire before sending the shutdown.
+    if timeout > 0 {
+        tokio::time::sleep(Duration::from_secs(timeout as u64)).await;
+    }
+
+    watcher.request_close(&event).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn midnight_is_measured_from_the_given_time() {
+        let now = Local.with_ymd_and_hms(2025, 3, 14, 23, 59, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(time_until_next_day_start(now, 0), Duration::from_secs(60));
+
+        let now = Local.with_ymd_and_hms(2025, 3, 14, 0, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(time_until_next_day_start(now, 0), Duration::from_secs(24 * 60 * 60));
+    }
+
+    #[test]
+    fn a_later_day_start_is_measured_from_the_given_time() {
+        let now = Local.with_ymd_and_hms(2025, 3, 14, 23, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(time_until_next_day_start(now, 4), Duration::from_secs(5 * 60 * 60));
+
+        let now = Local.with_ymd_and_hms(2025, 3, 14, 3, 0, 0).unwrap().with_timezone(&Utc);
+        assert_eq!(time_until_next_day_start(now, 4), Duration::from_secs(60 * 60));
+    }
+}
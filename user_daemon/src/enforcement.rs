@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use log::{debug, warn};
+use tokio::task::JoinHandle;
+
+use shared::dbus::Host;
+use shared::types::{Event, EventType};
+use crate::decision::{self, DisplayNameAction};
+use crate::watcher::Watcher;
+
+/// A pending block of the display name that is active on a host.
+struct EnforcementTimer {
+    display_name: String,
+    task: JoinHandle<()>,
+}
+
+/// Keeps one enforcement timer per host, so that the display names active on
+/// different watchers at the same time are enforced independently.
+pub struct Enforcer<W: Watcher> {
+    watcher: W,
+    timers: HashMap<Host, EnforcementTimer>,
+    /// The seconds before a block at which the user is warned, from the earliest warning.
+    warnings: Vec<u32>,
+}
+
+impl<W: Watcher> Enforcer<W> {
+    pub fn new(watcher: W) -> Self {
+        Enforcer { watcher, timers: HashMap::new(), warnings: Vec::new() }
+    }
+
+    /// Warn the user the given minutes before a display name is blocked.
+    /// Only the timers started afterwards are affected.
+    pub fn set_warnings(&mut self, minutes_before: &[u32]) {
+        let mut warnings = minutes_before.iter().map(|minutes| minutes * 60).collect::<Vec<u32>>();
+        warnings.sort_unstable_by(|a, b| b.cmp(a));
+        warnings.dedup();
+        self.warnings = warnings;
+    }
+
+    /// Apply the action decided for an event. Any event but a heartbeat means that
+    /// the previous display name of its host is no longer active, so its timer is cancelled.
+    pub fn handle(&mut self, event: &Event, action: DisplayNameAction) {
+        if let EventType::Heartbeat = event.event_type {
+            return;
+        }
+        self.cancel(&event.source);
+
+        let timeout = match action {
+            DisplayNameAction::Time(remaining_duration) => remaining_duration,
+            DisplayNameAction::Block => 0,
+            DisplayNameAction::Ignore => return,
+        };
+
+        // Spawn the task to close the display name upon timer expiration.
+        // The warnings that are already due when the timer starts are skipped.
+        let warnings = self.warnings.iter()
+            .copied()
+            .filter(|warning| *warning < timeout)
+            .collect::<Vec<u32>>();
+        let task = tokio::spawn({
+            let watcher = self.watcher.clone();
+            let event = event.clone();
+            async move {
+                let mut timeout = timeout;
+                for warning in warnings {
+                    tokio::time::sleep(Duration::from_secs((timeout - warning) as u64)).await;
+                    timeout = warning;
+                    if let Err(e) = watcher.warn(&event, warning).await {
+                        warn!("[TIMER] Failed to warn about '{}' on {}: {}", event.display_name, event.source, e);
+                    }
+                }
+
+                if let Err(e) = decision::block_display_name(watcher, event.clone(), timeout).await {
+                    warn!("[TIMER] Failed to close '{}' on {}: {}", event.display_name, event.source, e);
+                }
+            }
+        });
+        self.timers.insert(event.source.clone(), EnforcementTimer {
+            display_name: event.display_name.clone(),
+            task,
+        });
+    }
+
+    /// Cancel the timer of a host, if any.
+    pub fn cancel(&mut self, host: &Host) {
+        if let Some(timer) = self.timers.remove(host) {
+            debug!("[TIMER] Cancelling the timer of '{}' on {}.", timer.display_name, host);
+            timer.task.abort();
+        }
+    }
+
+    /// Cancel every timer, i.e. when no time is tracked anymore.
+    pub fn cancel_all(&mut self) {
+        for host in self.timers.keys().cloned().collect::<Vec<Host>>() {
+            self.cancel(&host);
+        }
+    }
+
+    /// The display name with a pending block on a host, if any.
+    pub fn pending(&self, host: &Host) -> Option<&str> {
+        self.timers.get(host)
+            .filter(|timer| !timer.task.is_finished())
+            .map(|timer| timer.display_name.as_str())
+    }
+}
+
+impl<W: Watcher> Drop for Enforcer<W> {
+    fn drop(&mut self) {
+        self.cancel_all();
+    }
+}
@@ -1,24 +1,379 @@
-use zbus::{fdo::Error as FdoError, interface};
-use tokio::sync::broadcast;
-use shared::types::Event;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use zbus::{fdo, interface, message::Header, object_server::SignalEmitter, Connection};
+use zbus::names::BusName;
+use tokio::sync::Notify;
+use log::warn;
+use shared::dbus::{DBus, Host};
+use shared::types::{Event, EventMetadata, EventType};
+
+/// The longest display name accepted from a watcher, in bytes.
+pub const MAX_DISPLAY_NAME_LEN: usize = 512;
+/// The longest text of the metadata accepted from a watcher, in bytes.
+pub const MAX_METADATA_LEN: usize = 4096;
+
+/// The events sent by the watchers that wait for the daemon. Any number of
+/// D-Bus calls push into the queue, while only the main loop takes from it.
+///
+/// Once `capacity` events wait, an event has to go before another is queued:
+/// a heartbeat if there is one, which carries nothing but liveness, otherwise the
+/// oldest event of a host that a later event of the same host supersedes, and only
+/// if there is none, the oldest event overall. So the latest focus of every host
+/// survives an overflow. Every dropped event is counted and reported as lag.
+pub struct EventQueue {
+    capacity: usize,
+    state: Mutex<QueueState>,
+    available: Notify,
+}
+
+#[derive(Default)]
+struct QueueState {
+    events: VecDeque<Event>,
+    /// The number of queued events per host, so that an overflow finds a
+    /// superseded event in a single pass over the queue.
+    per_host: HashMap<Host, usize>,
+    /// The number of queued heartbeats.
+    heartbeats: usize,
+    /// The events dropped since the lag was last taken.
+    lagged: u64,
+    /// The events dropped since startup.
+    dropped: u64,
+}
+
+impl EventQueue {
+    pub fn new(capacity: usize) -> Self {
+        EventQueue {
+            capacity: capacity.max(1),
+            state: Mutex::new(QueueState::default()),
+            available: Notify::new(),
+        }
+    }
+
+    /// Queue an event, making room according to the overflow policy.
+    /// Returns the number of events that wait, including this one.
+    pub fn push(&self, event: Event) -> usize {
+        let mut state = self.state.lock().unwrap();
+        if state.events.len() >= self.capacity {
+            let index = state.victim(&event);
+            if let Some(dropped) = state.remove(index) {
+                warn!("[EVENT BUS] The queue is full, dropping {:?} from {}.", dropped.event_type, dropped.source);
+            }
+            state.lagged += 1;
+            state.dropped += 1;
+        }
+        state.count(&event);
+        state.events.push_back(event);
+        let len = state.events.len();
+        drop(state);
+
+        self.available.notify_one();
+        len
+    }
+
+    /// Take the oldest event, if any waits.
+    pub fn pop(&self) -> Option<Event> {
+        self.state.lock().unwrap().remove(0)
+    }
+
+    /// Wait for the oldest event. Cancelling the wait never loses an event.
+    pub async fn recv(&self) -> Event {
+        loop {
+            if let Some(event) = self.pop() {
+                return event;
+            }
+            self.available.notified().await;
+        }
+    }
+
+    /// The number of events dropped since the last call.
+    pub fn take_lagged(&self) -> u64 {
+        std::mem::take(&mut self.state.lock().unwrap().lagged)
+    }
+
+    /// The number of events dropped since startup.
+    pub fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped
+    }
+}
+
+impl QueueState {
+    /// The index of the event that makes room for `incoming` in a full queue.
+    fn victim(&self, incoming: &Event) -> usize {
+        let position = if self.heartbeats > 0 {
+            self.events.iter().position(|e| matches!(e.event_type, EventType::Heartbeat))
+        } else {
+            // An event is superseded if its host has a later event queued or about to be.
+            self.events.iter().position(|e| e.source == incoming.source || self.per_host[&e.source] > 1)
+        };
+        position.unwrap_or(0)
+    }
+
+    fn count(&mut self, event: &Event) {
+        *self.per_host.entry(event.source.clone()).or_default() += 1;
+        if matches!(event.event_type, EventType::Heartbeat) {
+            self.heartbeats += 1;
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Event> {
+        let event = self.events.remove(index)?;
+        if let Some(count) = self.per_host.get_mut(&event.source) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_host.remove(&event.source);
+            }
+        }
+        if matches!(event.event_type, EventType::Heartbeat) {
+            self.heartbeats -= 1;
+        }
+        Some(event)
+    }
+}
+
+/// Check the fields of an event that the types alone do not constrain.
+pub fn validate_event(event: &Event) -> Result<(), String> {
+    if event.source == Host::UserDaemon {
+        return Err("The daemon does not watch anything itself.".to_string());
+    }
+    if event.display_name.len() > MAX_DISPLAY_NAME_LEN {
+        return Err(format!("The display name exceeds {} bytes.", MAX_DISPLAY_NAME_LEN));
+    }
+    if event.display_name.chars().any(char::is_control) {
+        return Err("The display name contains control characters.".to_string());
+    }
+    if matches!(event.event_type, EventType::FocusChange) && event.display_name.trim().is_empty() {
+        return Err("A focus change requires a display name.".to_string());
+    }
+    validate_metadata(&event.source, &event.event_type, &event.metadata)
+}
+
+/// Check the metadata against the schema of its host: only a focus change carries
+/// metadata, a browser tab only comes from the browser and an application only from the desktop.
+fn validate_metadata(host: &Host, event_type: &EventType, metadata: &EventMetadata) -> Result<(), String> {
+    if !matches!(event_type, EventType::FocusChange) {
+        return if metadata.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("A {:?} event carries no metadata.", event_type))
+        };
+    }
+
+    let text_len = metadata.url.len() + metadata.title.len() + metadata.app_id.len();
+    if text_len > MAX_METADATA_LEN {
+        return Err(format!("The metadata exceeds {} bytes.", MAX_METADATA_LEN));
+    }
+    if [&metadata.url, &metadata.title, &metadata.app_id].iter().any(|text| text.chars().any(char::is_control)) {
+        return Err("The metadata contains control characters.".to_string());
+    }
+    if metadata.tab_id < EventMetadata::NO_ID || metadata.window_id < EventMetadata::NO_ID {
+        return Err("The metadata contains a negative ID.".to_string());
+    }
+
+    match host {
+        Host::FirefoxWatcher => {
+            if metadata.tab_id == EventMetadata::NO_ID {
+                return Err("A focus change of the browser requires a tab ID.".to_string());
+            }
+            if !metadata.app_id.is_empty() {
+                return Err("The browser does not report application IDs.".to_string());
+            }
+        },
+        Host::GnomeExtension | Host::GnomeApplication => {
+            if metadata.tab_id != EventMetadata::NO_ID || !metadata.url.is_empty() {
+                return Err(format!("{} does not report browser tabs.", host));
+            }
+        },
+        Host::UserDaemon => {},
+    }
+    Ok(())
+}
+
+/// Verify that the sender of a message owns the well-known name of `host`,
+/// so that no other process can report activity on behalf of a watcher.
+async fn authenticate(conn: &Connection, header: &Header<'_>, host: &Host) -> fdo::Result<()> {
+    let Some(sender) = header.sender() else {
+        return Err(fdo::Error::AccessDenied("The caller has no unique name.".to_string()));
+    };
+
+    let name = BusName::try_from(DBus::host_name(host))
+        .map_err(|e| fdo::Error::Failed(e.to_string()))?;
+    let owner = fdo::DBusProxy::new(conn).await?
+        .get_name_owner(name)
+        .await
+        .ok();
+    if owner.as_ref().is_some_and(|owner| owner.as_str() == sender.as_str()) {
+        Ok(())
+    } else {
+        Err(fdo::Error::AccessDenied(format!("{} does not own the name of {}.", sender, host)))
+    }
+}
 
 /// Send events to the `User Daemon` to process. The daemon
-/// listens for any broadcasts and updates internal state accordingly.
+/// takes them from the queue and updates internal state accordingly.
 pub struct EventBus {
-    sender: broadcast::Sender<Event>,
+    queue: Arc<EventQueue>,
 }
 
 impl EventBus {
-    pub fn new(sender: broadcast::Sender<Event>) -> Self {
-        EventBus { sender }
+    pub fn new(queue: Arc<EventQueue>) -> Self {
+        EventBus { queue }
     }
 }
 
 #[interface(name = "com.activity_warden.EventBus")]
 impl EventBus {
-    async fn send_event_msg(&self, event: Event) -> zbus::fdo::Result<usize> {
-        self.sender.send(event).map_err(|e| {
-            FdoError::Failed(format!("Failed to send event: {}", e))
-        })
+    /// Queue an event for the daemon. Returns the number of events that wait.
+    /// The caller must own the well-known name of the host that the event claims.
+    async fn send_event_msg(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] conn: &Connection,
+        event: Event,
+    ) -> fdo::Result<usize> {
+        if let Err(e) = authenticate(conn, &header, &event.source).await {
+            warn!("[EVENT BUS] Rejecting a spoofed event: {}", e);
+            return Err(e);
+        }
+        if let Err(e) = validate_event(&event) {
+            warn!("[EVENT BUS] Rejecting an event from {}: {}", event.source, e);
+            return Err(fdo::Error::InvalidArgs(e));
+        }
+
+        Ok(self.queue.push(event))
+    }
+
+    /// The number of events that were dropped because the queue was full, since the daemon started.
+    #[zbus(property)]
+    fn dropped_events(&self) -> u64 {
+        self.queue.dropped()
+    }
+
+    /// Ask every watcher to send its current focus again, i.e. because the
+    /// daemon (re)started and knows nothing about the sessions in progress.
+    #[zbus(signal)]
+    pub async fn focus_requested(signal_emitter: &SignalEmitter<'_>) -> zbus::Result<()>;
+
+    /// The daemon fell behind and dropped `dropped` events since the last warning,
+    /// so the durations it reports may be off.
+    #[zbus(signal)]
+    pub async fn lagged(signal_emitter: &SignalEmitter<'_>, dropped: u64) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(host: Host, event_type: EventType, display_name: &str) -> Event {
+        let metadata = match (&host, &event_type) {
+            (Host::FirefoxWatcher, EventType::FocusChange) => EventMetadata::tab(1, "https://example.com/", "Example"),
+            _ => EventMetadata::default(),
+        };
+        Event {
+            event_type,
+            source: host,
+            display_name: display_name.to_string(),
+            metadata,
+        }
+    }
+
+    fn drain(queue: &EventQueue) -> Vec<String> {
+        std::iter::from_fn(|| queue.pop()).map(|e| e.display_name).collect()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn heartbeats_go_first() {
+        let queue = EventQueue::new(2);
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "a"));
+        queue.push(event(Host::GnomeExtension, EventType::Heartbeat, ""));
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "b"));
+
+        assert_eq!(drain(&queue), vec!["a", "b"]);
+        assert_eq!(queue.take_lagged(), 1);
+        assert_eq!(queue.take_lagged(), 0);
+    }
+
+    #[test]
+    fn the_latest_focus_of_every_host_survives() {
+        let queue = EventQueue::new(3);
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "a"));
+        queue.push(event(Host::GnomeExtension, EventType::FocusChange, "x"));
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "b"));
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "c"));
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "d"));
+
+        assert_eq!(drain(&queue), vec!["x", "c", "d"]);
+        assert_eq!(queue.dropped(), 2);
+    }
+
+    #[test]
+    fn an_event_of_another_host_replaces_a_superseded_one() {
+        let queue = EventQueue::new(3);
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "a"));
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "b"));
+        queue.push(event(Host::GnomeExtension, EventType::FocusChange, "x"));
+        queue.push(event(Host::GnomeApplication, EventType::FocusChange, "y"));
+
+        assert_eq!(drain(&queue), vec!["b", "x", "y"]);
+    }
+
+    #[test]
+    fn a_taken_event_no_longer_supersedes_anything() {
+        let queue = EventQueue::new(2);
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "a"));
+        queue.push(event(Host::GnomeExtension, EventType::FocusChange, "x"));
+        assert_eq!(queue.pop().map(|e| e.display_name), Some("a".to_string()));
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "b"));
+        queue.push(event(Host::GnomeApplication, EventType::FocusChange, "y"));
+
+        assert_eq!(drain(&queue), vec!["b", "y"]);
+    }
+
+    #[test]
+    fn events_are_validated() {
+        assert!(validate_event(&event(Host::FirefoxWatcher, EventType::FocusChange, "example.com")).is_ok());
+        assert!(validate_event(&event(Host::FirefoxWatcher, EventType::FocusLost, "")).is_ok());
+        assert!(validate_event(&event(Host::FirefoxWatcher, EventType::FocusChange, " ")).is_err());
+        assert!(validate_event(&event(Host::FirefoxWatcher, EventType::FocusChange, "a\nb")).is_err());
+        assert!(validate_event(&event(Host::UserDaemon, EventType::FocusChange, "example.com")).is_err());
+
+        let long = "a".repeat(MAX_DISPLAY_NAME_LEN + 1);
+        assert!(validate_event(&event(Host::FirefoxWatcher, EventType::FocusChange, &long)).is_err());
+    }
+
+    #[test]
+    fn metadata_follows_the_schema_of_its_host() {
+        let mut e = event(Host::FirefoxWatcher, EventType::FocusChange, "example.com");
+        e.metadata.tab_id = EventMetadata::NO_ID;
+        assert!(validate_event(&e).is_err());
+        e.metadata = EventMetadata { app_id: "org.gnome.Nautilus".to_string(), ..EventMetadata::tab(1, "", "") };
+        assert!(validate_event(&e).is_err());
+        e.metadata = EventMetadata::tab(1, "", &"a".repeat(MAX_METADATA_LEN + 1));
+        assert!(validate_event(&e).is_err());
+        e.metadata = EventMetadata::tab(-2, "", "");
+        assert!(validate_event(&e).is_err());
+
+        let mut e = event(Host::GnomeExtension, EventType::FocusChange, "Files");
+        e.metadata.app_id = "org.gnome.Nautilus".to_string();
+        e.metadata.window_id = 7;
+        assert!(validate_event(&e).is_ok());
+        e.metadata.tab_id = 1;
+        assert!(validate_event(&e).is_err());
+
+        let mut e = event(Host::FirefoxWatcher, EventType::FocusLost, "");
+        e.metadata.tab_id = 1;
+        assert!(validate_event(&e).is_err());
+    }
+
+    #[tokio::test]
+    async fn a_waiting_receiver_is_woken() {
+        let queue = Arc::new(EventQueue::new(1));
+        let receiver = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.recv().await.display_name }
+        });
+        tokio::task::yield_now().await;
+        queue.push(event(Host::FirefoxWatcher, EventType::FocusChange, "a"));
+
+        assert_eq!(receiver.await.unwrap(), "a");
+    }
+}
@@ -0,0 +1,140 @@
+use directories::BaseDirs;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use shared::dbus::{DBus, Host};
+
+/// The name of the systemd user unit of the daemon.
+pub const SYSTEMD_UNIT: &str = "activity_warden.service";
+/// The name under which the extension connects to the native messenger.
+pub const NATIVE_MESSENGER_NAME: &str = "com.activity_warden.firefox_native_messenger";
+/// The ID of the browser extension that may start the native messenger.
+const EXTENSION_ID: &str = "web_watcher@activity_warden.org";
+
+/// A file written by `install`, along with its contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallFile {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+/// The systemd user unit, which is started at login or by D-Bus activation,
+/// and only counts as started once the daemon notified its readiness.
+pub fn systemd_unit(daemon: &Path) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Activity Warden Daemon\n\
+         \n\
+         [Service]\n\
+         Type=notify\n\
+         ExecStart={}\n\
+         ExecReload=/bin/kill -HUP $MAINPID\n\
+         Restart=on-failure\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        daemon.display(),
+    )
+}
+
+/// The D-Bus service file, which starts the systemd unit once a client calls the daemon.
+pub fn dbus_service(daemon: &Path) -> String {
+    format!(
+        "[D-BUS Service]\n\
+         Name={}\n\
+         Exec={}\n\
+         SystemdService={}\n",
+        DBus::host_name(&Host::UserDaemon),
+        daemon.display(),
+        SYSTEMD_UNIT,
+    )
+}
+
+/// The manifest that allows the browser extension to start the native messenger.
+pub fn firefox_manifest(messenger: &Path) -> String {
+    let manifest = json!({
+        "name": NATIVE_MESSENGER_NAME,
+        "description": "Forwards the activity of the browser to the Activity Warden",
+        "path": messenger,
+        "type": "stdio",
+        "allowed_extensions": [EXTENSION_ID],
+    });
+    serde_json::to_string_pretty(&manifest).expect("The manifest is always serializable.")
+}
+
+/// The files that integrate the daemon and the messenger with the session,
+/// each at its location within the XDG directories of the user.
+/// Fails if the home directory of the user cannot be determined.
+pub fn install_files(daemon: &Path, messenger: &Path) -> Result<Vec<InstallFile>, String> {
+    let base_dirs = BaseDirs::new().ok_or("Failed to determine the home directory of the user.")?;
+    Ok(vec![
+        InstallFile {
+            path: base_dirs.config_dir().join("systemd/user").join(SYSTEMD_UNIT),
+            contents: systemd_unit(daemon),
+        },
+        InstallFile {
+            path: base_dirs.data_dir()
+                .join("dbus-1/services")
+                .join(format!("{}.service", DBus::host_name(&Host::UserDaemon))),
+            contents: dbus_service(daemon),
+        },
+        InstallFile {
+            path: base_dirs.home_dir()
+                .join(".mozilla/native-messaging-hosts")
+                .join(format!("{}.json", NATIVE_MESSENGER_NAME)),
+            contents: firefox_manifest(messenger),
+        },
+    ])
+}
+
+/// Write every file of `install_files`, replacing earlier versions. Returns the written paths.
+pub fn install(daemon: &Path, messenger: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut written = Vec::new();
+    for file in install_files(daemon, messenger)? {
+        if let Some(dir) = file.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        }
+        fs::write(&file.path, &file.contents)
+            .map_err(|e| format!("Failed to write {:?}: {}", file.path, e))?;
+        written.push(file.path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_dbus_service_starts_the_systemd_unit() {
+        let service = dbus_service(Path::new("/usr/bin/user_daemon"));
+        assert!(service.contains("Name=com.activity_warden.user_daemon\n"), "{}", service);
+        assert!(service.contains("SystemdService=activity_warden.service\n"), "{}", service);
+    }
+
+    #[test]
+    fn the_unit_waits_for_the_readiness() {
+        let unit = systemd_unit(Path::new("/usr/bin/user_daemon"));
+        assert!(unit.contains("Type=notify\n"), "{}", unit);
+        assert!(unit.contains("ExecStart=/usr/bin/user_daemon\n"), "{}", unit);
+    }
+
+    #[test]
+    fn the_manifest_names_the_messenger() {
+        let manifest: serde_json::Value = serde_json::from_str(
+            &firefox_manifest(Path::new("/usr/bin/firefox_native_messenger"))
+        ).unwrap();
+        assert_eq!(manifest["name"], NATIVE_MESSENGER_NAME);
+        assert_eq!(manifest["path"], "/usr/bin/firefox_native_messenger");
+        assert_eq!(manifest["allowed_extensions"][0], EXTENSION_ID);
+    }
+
+    #[test]
+    fn every_file_is_named_after_its_service() {
+        let files = install_files(Path::new("/a"), Path::new("/b")).unwrap();
+        assert!(files[0].path.ends_with("systemd/user/activity_warden.service"));
+        assert!(files[1].path.ends_with("dbus-1/services/com.activity_warden.user_daemon.service"));
+        assert!(files[2].path.ends_with("native-messaging-hosts/com.activity_warden.firefox_native_messenger.json"));
+    }
+}
@@ -0,0 +1,17 @@
+pub mod aggregation;
+pub mod auth;
+pub mod backup;
+pub mod clock;
+pub mod config;
+pub mod context;
+pub mod coverage;
+pub mod decision;
+pub mod enforcement;
+pub mod event_bus;
+pub mod install;
+pub mod pending;
+pub mod persistence;
+pub mod presence;
+pub mod proxy;
+pub mod sources;
+pub mod watcher;
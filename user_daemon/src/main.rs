@@ -1,285 +1,444 @@
-mod event_bus;
-mod proxy;
-mod persistence;
-mod context;
-
-use futures_lite::stream::StreamExt;
-use std::sync::{Arc, atomic::{Ordering, AtomicBool}};
-use serde_json::Value;
+use clap::{Parser, Subcommand};
+use std::fs;
+use std::path::PathBuf;
 use zbus::Result;
 use zbus::connection::Builder;
-use tokio::sync::broadcast;
+use std::sync::Arc;
 use tokio::signal::unix::{signal, SignalKind};
-use tokio::time::{sleep, sleep_until};
-use tokio::task::JoinHandle;
-use chrono::{DateTime, Datelike, Duration as CDuration, NaiveTime, Local, Utc};
-use shared::{dbus::{DBus, Host, Interface}, types::Event};
+use tokio::time::sleep_until;
+use tokio_util::sync::CancellationToken;
+use sd_notify::NotifyState;
+use shared::dbus::{DBus, Host, Interface};
+use shared::types::schema::FocusChange;
 use shared::types::EventType;
-use log::info;
+use shared::types::event::HEARTBEAT_INTERVAL_SECS;
+use shared::types::backup::BackupFormat;
+use log::{debug, info, warn, LevelFilter};
 use tokio::time::{self, Duration, Instant};
 use zbus::Connection;
-
-use crate::event_bus::EventBus;
-use crate::proxy::{FirefoxWatcherProxy, SuspendListenerProxy, ScreenSaverProxy};
-use crate::context::DaemonContext;
-
-/// The maximum size of the event bus before old messages are dropped.
-const CAPACITY: usize = 100;
-
-pub enum DisplayNameAction {
-    /// Close the given display name.
-    Block,
-    /// Set a timer for `u32` seconds.
-    Time(u32),
-    /// Track the time, but perform no actions.
-    Ignore,
+use zbus::object_server::SignalEmitter;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+
+use user_daemon::backup;
+use user_daemon::config::DaemonConfig;
+use user_daemon::context::DaemonContext;
+use user_daemon::decision;
+use user_daemon::enforcement::Enforcer;
+use user_daemon::event_bus::{EventBus, EventQueue};
+use user_daemon::install;
+use user_daemon::persistence::PersistenceLayer;
+use user_daemon::presence::{Presence, PresenceChange};
+use user_daemon::proxy::IdleMonitorProxy;
+use user_daemon::sources::{self, SystemSignal};
+use user_daemon::watcher::DBusWatchers;
+
+/// How often the daemon checks for pending changes whose cooling-off period has elapsed.
+const PENDING_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// How often the sessions in progress are stored, which bounds the time lost to a crash.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the idle time of the user is compared against the idle threshold.
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Parser)]
+#[command(about = "The central decision-maker of the Activity Warden.")]
+struct Cli {
+    /// Run a maintenance command instead of the daemon.
+    #[command(subcommand)]
+    command: Option<Command>,
 }
 
-
-/// Determine how much time must elapse until midnight in the local timezone.
-/// At this point, the daemon will wake up and close any stale messages.
-/// 
-/// This is only necessary to prevent durations from the previous day from
-/// impacting the following day. For example, a user spending 30 minutes on a
-/// display name around midnight would want that time properly split across the
-/// day boundary.
-fn instant_until_next_local_midnight() -> Instant {
-    // Fetch midnight tomorrow based on the local timezone.
-    let tomorrow_local = Local::now() + CDuration::days(1);
-    let next_midnight_local = DateTime::with_time(
-        &tomorrow_local, 
-        NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-        .single()
-        .expect("Failed to find a Local midnight.");
-
-    // Convert to UTC to account for date based edge cases.
-    let next_midnight_utc = next_midnight_local.with_timezone(&Utc);
-    Instant::now()
-        + std::time::Duration::from_secs(
-            (next_midnight_utc - Utc::now()).num_seconds() as u64
-        )
+#[derive(Subcommand)]
+enum Command {
+    /// Write all timers, sessions and settings to a file or standard output.
+    Export {
+        /// Either `json` or `csv`. The CSV format only contains the sessions.
+        #[arg(long, default_value_t = BackupFormat::Json)]
+        format: BackupFormat,
+        /// The destination file. Defaults to standard output.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Merge a previous export into the database. Existing records are kept.
+    /// Only while the daemon is stopped, since it would not notice the new records.
+    Import {
+        /// Either `json` or `csv`. The CSV format only contains the sessions.
+        #[arg(long, default_value_t = BackupFormat::Json)]
+        format: BackupFormat,
+        /// The file produced by `export`.
+        input: PathBuf,
+    },
+    /// Write the systemd user unit, the D-Bus service file and the Firefox
+    /// native messaging manifest, so that the daemon starts with the session.
+    Install {
+        /// The native messenger binary. Defaults to `firefox_native_messenger` next to this binary.
+        #[arg(long)]
+        messenger: Option<PathBuf>,
+    },
 }
 
+/// Inform any listeners (i.e. the GUI) that a new duration has been
+/// processed. This enables listeners to maintain state consistent
+/// with the daemon.
+async fn emit_focus_change(session_conn: &Connection, fc: &FocusChange) {
+    info!("[EMIT] FocusChange signal...");
+    session_conn.emit_signal(
+        None::<&str>,
+        DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext), 
+        DBus::interface_name(&Interface::DaemonContext),
+        "DurationChanged",
+        fc,
+    ).await.unwrap();
+}
 
-/// Determine if a particular display name is blocked or if a new timer should be set.
-fn is_display_name_blocked(context: &DaemonContext, host: &Host, display_name: &String) -> DisplayNameAction {
-    let today = chrono::Local::now();
-    let timers = context.timers.load_full();
-    for timer in (*timers).clone() {
-        if timer.display_name == *display_name {
-            let weekday = ((today.weekday() as usize) + 1) % 7;
-            info!("timer.allowed_days[{}] = {}", weekday, timer.allowed_days[weekday]);
-            if timer.allowed_days[weekday] {
-                if let Some(host_durations) = context.timer_durations.get(host) {
-                    // If the day is specified, and the timer is set to 0, no activity will be tracked.
-                    // As such, we implicitly know that the page is blocked.
-                    if timer.time_limit == 0 {
-                        info!("[BLOCKING] {}: timer is allotted 0 seconds.", display_name);
-                        return DisplayNameAction::Block;
-                    }
-
-                    let cur_duration = host_durations.get(display_name).unwrap_or(&0);
-                    if cur_duration >= &timer.time_limit {
-                        info!("[BLOCKING] {}: current duration ({}) is greater than the limit ({}).", display_name, cur_duration, timer.time_limit);
-                        return DisplayNameAction::Block;
-                    } else {
-                        info!("[NON-BLOCKING] {}: current duration ({}) is less than the limit ({}).", display_name, cur_duration, timer.time_limit);
-                        return DisplayNameAction::Time(timer.time_limit - cur_duration);
-                    }
-                }
-            }
-            info!("[BLOCKING] {}: the timer is disabled on {}.", display_name, today.format("%a"));
-            return DisplayNameAction::Block;
-        }
-    }   
-
-    // Otherwise, conclude that the name is not blocked.
-    DisplayNameAction::Ignore
+/// Let the clients know that the sessions in progress changed.
+async fn announce_active_sessions(context: &DaemonContext, emitter: &SignalEmitter<'_>) {
+    if let Err(e) = context.active_sessions_changed(emitter).await {
+        warn!("[EMIT] Failed to announce the active sessions: {}", e);
+    }
 }
 
-/// Close the provided display name.
-pub async fn block_display_name(
-    session_conn: Connection,
-    event: Event,
-    timeout: u32,
-) -> Result<()> {
-    // Wait for the timer to expire before sending the shutdown.
-    if timeout > 0 {
-        let dur = Duration::from_secs(timeout as u64);
-        time::sleep(dur).await;
+/// Ask the watchers to announce their current focus, which reopens the sessions in progress.
+async fn request_focus(emitter: &SignalEmitter<'_>) {
+    if let Err(e) = EventBus::focus_requested(emitter).await {
+        warn!("[PRESENCE] Failed to request the focus: {}", e);
     }
+}
 
-    // Actually connect to the respective `Watcher` to close the display name.
-    match event.source {
-        Host::FirefoxWatcher => {
-            let proxy = FirefoxWatcherProxy::builder(&session_conn)
-                .destination(DBus::host_name(&event.source))?
-                .path(DBus::object_path(&Host::FirefoxWatcher, &Interface::Watcher))?
-                .build()
-                .await?;
-
-            let metadata: Value = serde_json::from_str(&event.metadata)
-                .expect("Invalid metadata structure");
-            let _ = proxy.request_close(&metadata.to_string()).await;
-        },
-        _ => { panic!("Received unexpected host for the FocusChange event.") }
-    };
+/// Wake up at the start of the next day according to the trusted time of the context.
+fn instant_until_next_day_start(context: &DaemonContext) -> Instant {
+    let day_start_hour = context.config().tracking.day_start_hour;
+    Instant::now() + decision::time_until_next_day_start(context.now(), day_start_hour)
+}
 
-    Ok(())
+/// Whether a daemon owns its name on the session bus, i.e. because it is running.
+async fn daemon_is_running() -> bool {
+    let Ok(conn) = Connection::session().await else {
+        return false;
+    };
+    let Ok(dbus) = DBusProxy::new(&conn).await else {
+        return false;
+    };
+    let Ok(name) = BusName::try_from(DBus::host_name(&Host::UserDaemon)) else {
+        return false;
+    };
+    dbus.name_has_owner(name).await.unwrap_or(false)
 }
 
-/// Inform any listeners (i.e. the GUI) that a new duration has been
-/// processed. This enables listeners to maintain state consistent
-/// with the daemon.
-pub async fn emit_focus_change(
-    context: &mut DaemonContext,
-    session_conn: &Connection,
-    event: Event,
-    set_last_event: bool,
-) {
-    let fc = context.update_event_durations(&event, set_last_event);
-    if fc.is_some() {
-        info!("[EMIT] FocusChange signal...");
-        session_conn.emit_signal(
-            None::<&str>,
-            DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext), 
-            DBus::interface_name(&Interface::DaemonContext),
-            "DurationChanged",
-            &fc.unwrap(),
-        ).await.unwrap();
+/// Execute a maintenance command directly against the database or the session setup.
+/// These commands do not require the daemon to be running, and those that
+/// modify the database refuse to run behind the back of a running daemon.
+async fn run_command(command: Command, config: &DaemonConfig) -> std::result::Result<(), String> {
+    let storage = &config.storage;
+    match command {
+        Command::Export { format, output } => {
+            let pl = PersistenceLayer::new(storage);
+            let data = backup::export_data(&pl, format)?;
+            match output {
+                Some(path) => fs::write(path, data).map_err(|e| e.to_string())?,
+                None => println!("{}", data),
+            }
+        },
+        Command::Import { format, input } => {
+            if daemon_is_running().await {
+                return Err("The daemon is running. Stop it first, or import through its `ImportData` method.".to_string());
+            }
+            let data = fs::read_to_string(input).map_err(|e| e.to_string())?;
+            let pl = PersistenceLayer::new(storage);
+            let summary = backup::import_data(&pl, format, &data, config.tracking.day_start_hour)?;
+            println!(
+                "Imported {} timers, {} sessions and {} daily totals ({}, {} and {} already present).",
+                summary.timers_imported,
+                summary.focus_changes_imported,
+                summary.daily_summaries_imported,
+                summary.timers_skipped,
+                summary.focus_changes_skipped,
+                summary.daily_summaries_skipped,
+            );
+        },
+        Command::Install { messenger } => {
+            let daemon = std::env::current_exe().map_err(|e| e.to_string())?;
+            let messenger = messenger.unwrap_or_else(|| daemon.with_file_name("firefox_native_messenger"));
+            if !messenger.exists() {
+                eprintln!("Warning: the native messenger {:?} does not exist yet.", messenger);
+            }
+            for path in install::install(&daemon, &messenger)? {
+                println!("Wrote {}", path.display());
+            }
+            println!("Run `systemctl --user daemon-reload && systemctl --user enable --now {}` to start the daemon.", install::SYSTEMD_UNIT);
+        },
     }
+
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Build the rust logger.
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    // Build the rust logger. Unless `RUST_LOG` is set, every record passes the logger
+    // itself, so that the configured level can change while the daemon runs.
+    let mut logger = env_logger::Builder::new();
+    match std::env::var("RUST_LOG") {
+        Ok(filters) => logger.parse_filters(&filters),
+        Err(_) => logger.filter_level(LevelFilter::Trace),
+    };
+    logger.init();
+    let config = DaemonConfig::load();
+    config.logging.apply();
+
+    let cli = Cli::parse();
+    if let Some(command) = cli.command {
+        if let Err(e) = run_command(command, &config).await {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
     // Expose the daemon on the session DBus.
-    let mut context = DaemonContext::new();
-    let sender = broadcast::Sender::new(CAPACITY);
-    let mut receiver = sender.subscribe();
-    let event_channel = EventBus::new(sender);
+    let event_queue = Arc::new(EventQueue::new(config.daemon.event_capacity));
+    let mut context = DaemonContext::new(config);
+    context.record_startup();
+    let event_channel = EventBus::new(event_queue.clone());
     let session_conn = Builder::session()?
         .name(DBus::host_name(&Host::UserDaemon))?
         .serve_at(DBus::object_path(&Host::UserDaemon, &Interface::EventBus), event_channel)?
-        .serve_at(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext), context.clone())?
+        .serve_at(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext), context)?
         .build()
         .await?;
 
-    let shutdown_flag = Arc::new(AtomicBool::new(false));
-    let flag_clone = shutdown_flag.clone();
+    // Operate on the served context so that D-Bus queries observe the live state.
+    let context_ref = session_conn
+        .object_server()
+        .interface::<_, DaemonContext>(DBus::object_path(&Host::UserDaemon, &Interface::DaemonContext))
+        .await?;
 
-    // Listen for Ctrl + C to shut down the daemon.
+    // The sessions in progress are unknown after a start, so ask the watchers to announce their focus.
+    let event_bus_ref = session_conn
+        .object_server()
+        .interface::<_, EventBus>(DBus::object_path(&Host::UserDaemon, &Interface::EventBus))
+        .await?;
+    EventBus::focus_requested(event_bus_ref.signal_emitter()).await?;
+
+    // The name is taken, so systemd may consider the daemon, and with it a D-Bus activation, started.
+    if let Err(e) = sd_notify::notify(false, &[NotifyState::Ready]) {
+        warn!("Failed to notify systemd of the readiness: {}", e);
+    }
+
+    // Listen for SIGTERM and Ctrl + C to shut down the daemon.
+    let shutdown = CancellationToken::new();
+    let shutdown_trigger = shutdown.clone();
     tokio::spawn(async move {
         let mut sigterm = signal(SignalKind::terminate())
             .expect("failed to register SIGTERM handler");
+        let mut sigint = signal(SignalKind::interrupt())
+            .expect("failed to register SIGINT handler");
 
-        sigterm.recv().await; // Wait for SIGTERM
-
-        info!("SIGTERM received, setting shutdown flag...");
-        flag_clone.store(true, Ordering::SeqCst);
+        tokio::select! {
+            _ = sigterm.recv() => info!("SIGTERM received, shutting down..."),
+            _ = sigint.recv() => info!("SIGINT received, shutting down..."),
+        }
+        shutdown_trigger.cancel();
     });
 
-    // Listen for events that imply the computer is turning off.
-    let system_conn = zbus::Connection::system().await?;
-    let suspend_proxy = SuspendListenerProxy::new(&system_conn).await.unwrap();
-    let mut sleep_stream = suspend_proxy.receive_prepare_for_sleep().await.unwrap();
+    // Listen for suspends and screen locks from whichever integrations are available.
+    let mut system_signals = sources::start(&session_conn).await;
+
+    // Reload the configuration on request.
+    let mut sighup = signal(SignalKind::hangup())
+        .expect("failed to register SIGHUP handler");
+
+    // Follow the input of the user to stop counting while they are away.
+    let idle_monitor = match IdleMonitorProxy::new(&session_conn).await {
+        Ok(proxy) if proxy.get_idletime().await.is_ok() => Some(proxy),
+        _ => {
+            warn!("[SOURCES] The idle monitor of GNOME is unavailable, so idle time counts.");
+            None
+        }
+    };
 
-    let screen_saver_proxy = ScreenSaverProxy::new(&session_conn).await.unwrap();
-    let mut screen_stream = screen_saver_proxy.receive_ActiveChanged().await.unwrap();
+    // Pause the tracking while the system sleeps, the screen is locked or the user is idle.
+    let mut presence = Presence::default();
 
     // Set some additional intervals to keep the event loop from becoming stale.
-    let mut midnight_dur = instant_until_next_local_midnight();
-    let timeout_dur = tokio::time::Duration::from_millis(500);
-    let mut timer_task: Option<JoinHandle<()>> = None;
+    let mut day_start_dur = instant_until_next_day_start(&*context_ref.get().await);
+    let mut pending_interval = time::interval(PENDING_CHECK_INTERVAL);
+    let mut coverage_interval = time::interval(Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+    let mut checkpoint_interval = time::interval(CHECKPOINT_INTERVAL);
+    let mut idle_interval = time::interval(IDLE_CHECK_INTERVAL);
+    let mut enforcer = Enforcer::new(DBusWatchers::new(session_conn.clone()));
     loop {
         tokio::select! {
             // Listen to the event bus to receive events from watchers.
-            event_result = receiver.recv() => {
-                let event = if event_result.is_ok() { 
-                    event_result.unwrap()
-                } else {
+            event = event_queue.recv() => {
+                // Let the clients know if events had to be dropped since the last one.
+                let lagged = event_queue.take_lagged();
+                if lagged > 0 {
+                    warn!("[EVENT BUS] Dropped {} events, the durations may be off.", lagged);
+                    if let Err(e) = EventBus::lagged(event_bus_ref.signal_emitter(), lagged).await {
+                        warn!("[EVENT BUS] Failed to report the lag: {}", e);
+                    }
+                    if let Err(e) = event_bus_ref.get().await.dropped_events_changed(event_bus_ref.signal_emitter()).await {
+                        warn!("[EVENT BUS] Failed to report the dropped events: {}", e);
+                    }
+                }
+
+                // No session opens while the user is away. The watchers announce their focus once they return.
+                if presence.is_away() && matches!(event.event_type, EventType::FocusChange) {
+                    debug!("[PRESENCE] Ignoring the focus of '{}' while away.", event.display_name);
                     continue;
-                };
-                
-                // Cancel the timer future on receipt of a new event.
-                if let Some(tt) = &timer_task {
-                    tt.abort();
                 }
 
                 // Process the incoming event.
-                context.reset_daily_state();
-                match &event.event_type {
-                    EventType::FocusChange => {
-                        let action = is_display_name_blocked(&context, &event.source, &event.display_name);
-                        match action {
-                            DisplayNameAction::Time(remaining_duration) => {
-                                emit_focus_change(&mut context, &session_conn, event.clone(), true).await;
-                                
-                                // Spawn the task to close the display name upon timer expiration.
-                                timer_task = Some(tokio::spawn({
-                                    let session_conn = session_conn.clone();
-                                    let event = event.clone();
-                                    async move {
-                                        let _ = block_display_name(session_conn, event, remaining_duration).await;
-                                    }
-                                }));
-                            }
-                            DisplayNameAction::Block => {
-                                let _ = block_display_name(session_conn.clone(), event, 0).await;
-                            },
-                            DisplayNameAction::Ignore => {
-                                emit_focus_change(&mut context, &session_conn, event, true).await;
-                            },
-                        }
+                let mut context = context_ref.get_mut().await;
+                let outcome = decision::process_event(&mut context, &event);
+                if let Some(fc) = outcome.focus_change {
+                    emit_focus_change(&session_conn, &fc).await;
+                }
+
+                // Replace the timers of the host that sent the event and of the hosts it paused or resumed.
+                enforcer.set_warnings(&context.config().warnings.minutes_before);
+                for (event, action) in outcome.actions {
+                    enforcer.handle(&event, action);
+                }
+
+                if !matches!(event.event_type, EventType::Heartbeat) {
+                    announce_active_sessions(&context, context_ref.signal_emitter()).await;
+                }
+            }
+
+            Some(signal) = system_signals.recv() => {
+                info!("[PRESENCE] {:?}", signal);
+                let mut context = context_ref.get_mut().await;
+                let change = match signal {
+                    SystemSignal::Sleep(start) => {
+                        context.forgive_suspend();
+
+                        // The sleep does not advance while the system is suspended.
+                        day_start_dur = instant_until_next_day_start(&context);
+                        presence.on_sleep(start)
                     },
-                    EventType::FocusLost => {
-                        emit_focus_change(&mut context, &session_conn, event, false).await;
+                    SystemSignal::ScreenLock(active) => presence.on_screen_lock(active),
+                };
+
+                match change {
+                    Some(PresenceChange::Away) => {
+                        for fc in context.clear_last_event() {
+                            emit_focus_change(&session_conn, &fc).await;
+                        }
+                        enforcer.cancel_all();
+                        announce_active_sessions(&context, context_ref.signal_emitter()).await;
                     },
-                    EventType::AFK => { /* Implement in the future. */ },
+                    Some(PresenceChange::Returned) => request_focus(event_bus_ref.signal_emitter()).await,
+                    None => {},
                 }
             }
 
-            suspend = sleep_stream.next() => {
-                let resp = suspend.unwrap();
-                let suspend_args = resp.args()?;
-                info!("[SUSPEND]: {:?}", suspend_args.start);
+            // Wake up at the start of the day to ensure that state is properly stored across the day boundary.
+            _ = sleep_until(day_start_dur) => {
+                let mut context = context_ref.get_mut().await;
+                if context.reset_daily_state() {
+                    // The sessions in progress continue on the budget of the new day.
+                    for (event, action) in decision::session_actions(&context) {
+                        enforcer.handle(&event, action);
+                    }
+                    announce_active_sessions(&context, context_ref.signal_emitter()).await;
+                }
+
+                // Keep the raw history within the retention window.
+                context.apply_retention();
 
-                context.clear_last_event();
+                // Set the new timeout to the start of the following day.
+                day_start_dur = instant_until_next_day_start(&context);
             }
 
-            screen_active = screen_stream.next() => {
-                let resp = screen_active.unwrap();
-                let screen_args = resp.args()?;
-                info!("[SCREEN OFF]: {:?}", screen_args.active);
+            // Look for watchers that went silent and for changes of the clock.
+            _ = coverage_interval.tick() => {
+                let mut context = context_ref.get_mut().await;
+                context.check_coverage();
 
-                context.clear_last_event();
+                // The trusted time may have followed a jump of the clock,
+                // and a reload of the configuration may have moved the day start.
+                day_start_dur = instant_until_next_day_start(&context);
             }
-            
-            // Wake up at midnight to ensure that state is properly stored across the day boundary.
-            _ = sleep_until(midnight_dur) => {
-                let last_event_map = context.last_event.clone();
-                for (_, last_event) in last_event_map.iter() {
-                    context.update_event_durations(
-                        &last_event.event,
-                        true
-                    );
+
+            // Store the sessions in progress in case the daemon stops unexpectedly.
+            _ = checkpoint_interval.tick() => {
+                context_ref.get().await.checkpoint_sessions();
+            }
+
+            // End the sessions once the user is idle, and ask for the focus again once they return.
+            _ = idle_interval.tick() => {
+                let threshold = context_ref.get().await.config().tracking.idle_threshold_minutes * 60;
+                let idle_secs = match &idle_monitor {
+                    Some(idle_monitor) if threshold > 0 => match idle_monitor.get_idletime().await {
+                        Ok(idle_ms) => (idle_ms / 1000) as u32,
+                        Err(e) => {
+                            debug!("[IDLE] Failed to query the idle time: {}", e);
+                            continue;
+                        }
+                    },
+                    _ => 0,
+                };
+
+                match presence.on_idle(threshold > 0 && idle_secs >= threshold) {
+                    Some(PresenceChange::Away) => {
+                        info!("[IDLE] No input for {} seconds, ending the sessions in progress.", idle_secs);
+                        let mut context = context_ref.get_mut().await;
+                        for fc in context.end_idle_sessions(idle_secs) {
+                            emit_focus_change(&session_conn, &fc).await;
+                        }
+                        enforcer.cancel_all();
+                        announce_active_sessions(&context, context_ref.signal_emitter()).await;
+                    },
+                    Some(PresenceChange::Returned) => {
+                        info!("[IDLE] The user returned, asking the watchers for their focus.");
+                        request_focus(event_bus_ref.signal_emitter()).await;
+                    },
+                    None => {},
                 }
+            }
 
-                // Set the new timeout to midnight on the following day.
-                midnight_dur = instant_until_next_local_midnight();
+            // Read the configuration file again.
+            _ = sighup.recv() => {
+                info!("SIGHUP received, reloading the configuration...");
+                let mut context = context_ref.get_mut().await;
+                // A signal carries no edit session, so a locked daemon keeps its day start.
+                match context.reload_config_file(false) {
+                    Ok(pending_changed) => {
+                        if let Err(e) = context.announce_config(context_ref.signal_emitter(), pending_changed).await {
+                            warn!("[CONFIG] Failed to announce the new configuration: {}", e);
+                        }
+                    },
+                    Err(e) => warn!("[CONFIG] Keeping the current configuration: {}", e),
+                }
             }
-        
-            // Wake up every `timeout_dur` milliseconds to check the shutdown flag.
-            _ = sleep(timeout_dur) => {
-                if shutdown_flag.load(Ordering::SeqCst) {
-                    break;
+
+            // Apply the changes that have waited out the cooling-off period.
+            _ = pending_interval.tick() => {
+                let mut context = context_ref.get_mut().await;
+                if let Err(e) = context.apply_due_changes(context_ref.signal_emitter()).await {
+                    warn!("[PENDING] Failed to announce the applied changes: {}", e);
                 }
             }
+
+            _ = shutdown.cancelled() => break,
         }
     }
 
+    // Record the sessions in progress, which the watchers announce again on the next start.
+    let _ = sd_notify::notify(false, &[NotifyState::Stopping]);
+    enforcer.cancel_all();
+    let mut context = context_ref.get_mut().await;
+    for fc in context.shutdown() {
+        emit_focus_change(&session_conn, &fc).await;
+    }
+    announce_active_sessions(&context, context_ref.signal_emitter()).await;
+    drop(context);
+
+    // Let a new instance take over the name right away.
+    if let Err(e) = session_conn.release_name(DBus::host_name(&Host::UserDaemon)).await {
+        warn!("Failed to release the bus name: {}", e);
+    }
     info!("Terminating the User Daemon!");
     Ok(())
 }
\ No newline at end of file
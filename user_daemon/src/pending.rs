@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use shared::types::daemon::UnlockMethod;
+use shared::types::schema::Timer;
+
+/// A change that loosens the restrictions, held back during the cooling-off period.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Change {
+    /// Unlock the daemon. The credential was already verified upon the request.
+    Unlock {
+        method: UnlockMethod,
+        reason: String,
+    },
+    UpdateTimer(Timer),
+    DeleteTimer(Timer),
+    /// Shorten the cooling-off period to `minutes`. The current period
+    /// stays in force until then, even if the daemon restarts in between.
+    CoolingOff {
+        minutes: u32,
+    },
+    /// Move the start of the tracking day to `hour`, which may grant a second
+    /// budget on the same day. The current day start stays in force until then.
+    DayStart {
+        hour: u32,
+    },
+}
+
+impl Change {
+    /// The timer that the change applies to, if any.
+    pub fn timer(&self) -> Option<&Timer> {
+        match self {
+            Change::Unlock { .. } | Change::CoolingOff { .. } | Change::DayStart { .. } => None,
+            Change::UpdateTimer(timer) | Change::DeleteTimer(timer) => Some(timer),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PendingChange {
+    pub id: i64,
+    pub change: Change,
+    pub requested_at: DateTime<Utc>,
+    pub apply_at: DateTime<Utc>,
+}
+
+/// Whether replacing the `old` timer with the `new` one allows more time on any day.
+/// A day on which a timer is disabled allows no time at all.
+pub fn is_loosening(old: &Timer, new: &Timer) -> bool {
+    new.time_limit > old.time_limit
+        || old.allowed_days.iter()
+            .zip(&new.allowed_days)
+            .any(|(&was_allowed, &is_allowed)| !was_allowed && is_allowed)
+}
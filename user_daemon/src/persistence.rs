@@ -1,23 +1,33 @@
-use chrono::{DurationRound, Local, TimeDelta};
+use chrono::{DateTime, NaiveDate, Utc};
 use rusqlite::params;
-use directories::BaseDirs;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use log::{debug, info};
-use sha2::{Sha256, Digest};
 
 use shared::dbus::Host;
-use shared::types::daemon::DurationMap;
-use shared::types::schema::{AWTables, FocusChange, Password, QueryType, Timer};
+use shared::types::daemon::{CoverageGap, DurationMap, GapKind, UnlockEvent};
+use shared::types::day;
+use shared::types::schema::{AWTables, DailySummary, FocusChange, Password, QueryType, Timer};
+use crate::auth::AuthState;
+use crate::config::StorageConfig;
+use crate::pending::{Change, PendingChange};
 use std::path::PathBuf;
 use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-/// The name of the SQLite DB file used by the daemon.
-const DB_FILE_NAME: &str = "aw_records.db3";
-const STORE_DIR: &str = "activity_warden";
 const PASSWORD_FILE_NAME: &str = "password_hash";
+const LOCK_STATE_FILE_NAME: &str = "lock_state";
+const PARTNER_SECRET_FILE_NAME: &str = "partner_secret";
+const LIVENESS_FILE_NAME: &str = "liveness";
+const SETTINGS_IN_FORCE_FILE_NAME: &str = "settings_in_force";
+const AUTH_STATE_FILE_NAME: &str = "auth_state";
+
+/// The number of in-memory databases opened so far, used to keep them apart.
+static NEXT_MEMORY_DB: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Clone)]
 pub struct PersistenceLayer {
@@ -28,13 +38,16 @@ pub struct PersistenceLayer {
     data_root: PathBuf,
 }
 
-impl PersistenceLayer {
-    pub fn new() -> Self {
-        let base_dirs = BaseDirs::new().unwrap();
+impl Default for PersistenceLayer {
+    fn default() -> Self {
+        Self::new(&StorageConfig::default())
+    }
+}
 
+impl PersistenceLayer {
+    pub fn new(storage: &StorageConfig) -> Self {
         // Initialize the directory for the local file store.
-        let mut data_root = base_dirs.data_dir().to_path_buf();
-        data_root.push(STORE_DIR);
+        let data_root = storage.data_root();
         if !data_root.exists() {
             fs::create_dir_all(&data_root).expect("Failed to initialize the local file store");
         }
@@ -42,10 +55,38 @@ impl PersistenceLayer {
         info!("Creating local file store at {:?}.", data_root);
 
         // Open the connection to the database from the local file.
-        let db_path = data_root.join(DB_FILE_NAME);
+        let db_path = storage.database_path();
+        if let Some(db_dir) = db_path.parent() {
+            fs::create_dir_all(db_dir).expect("Failed to initialize the database directory");
+        }
         let manager = SqliteConnectionManager::file(&db_path);
         let pool = Pool::new(manager).expect("Failed to create sqlite pool");
 
+        Self::with_pool(pool, data_root)
+    }
+
+    /// Keep the database in memory, which is discarded along with the layer.
+    /// The remaining files are stored in `data_root`, which must exist.
+    pub fn in_memory(data_root: PathBuf) -> Self {
+        // Every layer receives its own database, which all connections of the pool share.
+        let id = NEXT_MEMORY_DB.fetch_add(1, Ordering::Relaxed);
+        let manager = SqliteConnectionManager::file(format!(
+            "file:activity_warden_{}_{}?mode=memory&cache=shared",
+            std::process::id(),
+            id,
+        ));
+
+        // The database only lives as long as one of its connections, so never close them.
+        let pool = Pool::builder()
+            .max_lifetime(None)
+            .idle_timeout(None)
+            .build(manager)
+            .expect("Failed to create sqlite pool");
+
+        Self::with_pool(pool, data_root)
+    }
+
+    fn with_pool(pool: Pool<SqliteConnectionManager>, data_root: PathBuf) -> Self {
         // Determine the path of the current file.
         let sql_root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
         let mut this = PersistenceLayer { pool, sql_root, data_root };
@@ -56,29 +97,42 @@ impl PersistenceLayer {
     /// Initialize all database tables.
     fn init_db(&mut self) {
         let conn = self.pool.get().expect("Failed to obtain SQLite connection");
-        for table in vec![AWTables::FocusChanges, AWTables::Timers] {
+        for table in [AWTables::FocusChanges, AWTables::DailySummaries, AWTables::Timers, AWTables::UnlockEvents, AWTables::PendingChanges, AWTables::CoverageGaps, AWTables::ActiveSessions] {
             if !conn.table_exists(None::<&str>, &table.to_string()).unwrap() {
                 let create_query = self.load_sql(
                     &table, 
                     &QueryType::CREATE
                 );
                 conn.execute(&create_query, ()).unwrap();
-                info!("Creating table {} in the DB.", table.to_string());
+                info!("Creating table {} in the DB.", table);
             } else {
-                info!("Table {} already exists in the DB.", table.to_string());
+                info!("Table {} already exists in the DB.", table);
             }
         }
     }
 
     fn load_sql(&self, table: &AWTables, action: &QueryType) -> String {
         let file_name = format!("{}_{}.sql", table, action);
+        debug!("Loading a {} query for the {} table.", action, table);
+        self.read_sql_file(&file_name)
+    }
+
+    /// Load a specialized variant of an `action` on a `table`. These queries
+    /// follow the naming scheme `{table}_{action}_{name}.sql`.
+    fn load_named_sql(&self, table: &AWTables, action: &QueryType, name: &str) -> String {
+        let file_name = format!("{}_{}_{}.sql", table, action, name);
+        debug!("Loading the '{}' {} query for the {} table.", name, action, table);
+        self.read_sql_file(&file_name)
+    }
+
+    fn read_sql_file(&self, file_name: &str) -> String {
         let mut path = self.sql_root.clone();
         
         // Queries reside in the `sql` subdirectory.
         path.push("sql");
         path.push(file_name);
 
-        debug!("Loading a {} query for the {} table at path: {:?}.", action, table, path);
+        debug!("Reading SQL at path: {:?}.", path);
         fs::read_to_string(path).unwrap()
     }
 
@@ -93,18 +147,10 @@ impl PersistenceLayer {
         }
     }
 
-    /// Set a new password for the User Daemon. The password is hashed
-    /// and written into a file.
-    /// 
-    /// Note that the password argument should be a raw, unhashed password.
-    pub fn set_new_password(&self, password: Password) {
-        let mut hasher = Sha256::new();
-        hasher.update(password);
-    
-        let digest = hasher.finalize();
-        let password_hash = hex::encode(digest);
-        debug!("[PASSWORD] Setting the new password hash to {}.", password_hash);
-
+    /// Store the hash of the password for the User Daemon. The hash is
+    /// written into a file, so the argument must never be a raw password.
+    pub fn set_password_hash(&self, password_hash: &str) {
+        debug!("[PASSWORD] Storing a new password hash.");
         let path = self.data_root.join(PASSWORD_FILE_NAME);
         fs::write(path, password_hash).unwrap();
     }
@@ -114,24 +160,240 @@ impl PersistenceLayer {
         let path = self.data_root.join(PASSWORD_FILE_NAME);
         fs::remove_file(path).unwrap();
     }
+
+    /// Whether the daemon is locked. Before the lock state was stored on its own,
+    /// the daemon was locked exactly when a password existed. Without a password,
+    /// the lock could never be released, so the daemon is never locked.
+    pub fn get_lock_state(&self) -> bool {
+        if self.get_cur_password().is_none() {
+            return false;
+        }
+
+        let path = self.data_root.join(LOCK_STATE_FILE_NAME);
+        match fs::read_to_string(path) {
+            Ok(state) => state.trim() == "locked",
+            Err(_) => true,
+        }
+    }
+
+    pub fn set_lock_state(&self, is_locked: bool) {
+        debug!("[PASSWORD] Setting the lock state to {}.", is_locked);
+        let path = self.data_root.join(LOCK_STATE_FILE_NAME);
+        let state = if is_locked { "locked" } else { "unlocked" };
+        fs::write(path, state).unwrap();
+    }
+
+    /// The failed attempts and the last accepted partner code. A missing or
+    /// unreadable file starts afresh.
+    pub fn get_auth_state(&self) -> AuthState {
+        let path = self.data_root.join(AUTH_STATE_FILE_NAME);
+        fs::read_to_string(path).ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn set_auth_state(&self, state: &AuthState) {
+        let path = self.data_root.join(AUTH_STATE_FILE_NAME);
+        fs::write(path, serde_json::to_string(state).unwrap()).unwrap();
+    }
+
+    pub fn get_partner_secret(&self) -> Option<String> {
+        let path = self.data_root.join(PARTNER_SECRET_FILE_NAME);
+        fs::read_to_string(path).ok()
+    }
+
+    /// Store the base32 secret shared with the accountability partner. Unlike the
+    /// password, the secret is stored as-is, so the file is only readable by the user.
+    pub fn set_partner_secret(&self, secret: &str) {
+        debug!("[PARTNER] Storing a new partner secret.");
+        let path = self.data_root.join(PARTNER_SECRET_FILE_NAME);
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .unwrap();
+        file.write_all(secret.as_bytes()).unwrap();
+    }
+
+    pub fn remove_partner_secret(&self) {
+        debug!("[PARTNER] Removing the partner secret.");
+        let path = self.data_root.join(PARTNER_SECRET_FILE_NAME);
+        fs::remove_file(path).unwrap();
+    }
+
+    /// The last moment at which the daemon was known to be running, and
+    /// whether it stopped cleanly afterwards.
+    pub fn get_liveness(&self) -> Option<(DateTime<Utc>, bool)> {
+        let path = self.data_root.join(LIVENESS_FILE_NAME);
+        let contents = fs::read_to_string(path).ok()?;
+        let (state, time) = contents.trim().split_once(' ')?;
+        let time = DateTime::parse_from_rfc3339(time).ok()?.with_timezone(&Utc);
+        Some((time, state == "stopped"))
+    }
+
+    pub fn set_liveness(&self, time: DateTime<Utc>, stopped: bool) {
+        let path = self.data_root.join(LIVENESS_FILE_NAME);
+        let state = if stopped { "stopped" } else { "running" };
+        fs::write(path, format!("{} {}", state, time.to_rfc3339())).unwrap();
+    }
+
+    /// The cooling-off period in minutes and the day start hour in force, which differ
+    /// from the configuration file while a change to them waits for the cooling-off period.
+    pub fn get_settings_in_force(&self) -> Option<(u32, u32)> {
+        let path = self.data_root.join(SETTINGS_IN_FORCE_FILE_NAME);
+        let contents = fs::read_to_string(path).ok()?;
+        let (cooling_off_minutes, day_start_hour) = contents.trim().split_once(' ')?;
+        Some((cooling_off_minutes.parse().ok()?, day_start_hour.parse().ok()?))
+    }
+
+    pub fn set_settings_in_force(&self, cooling_off_minutes: u32, day_start_hour: u32) {
+        let path = self.data_root.join(SETTINGS_IN_FORCE_FILE_NAME);
+        fs::write(path, format!("{} {}", cooling_off_minutes, day_start_hour)).unwrap();
+    }
+
+    /// Record a period of missing coverage. A gap without an `end` is still open.
+    /// Returns the identifier of the gap.
+    pub fn insert_coverage_gap(
+        &self,
+        kind: GapKind,
+        host: &Host,
+        start: DateTime<Utc>,
+        end: Option<DateTime<Utc>>,
+        detail: &str,
+    ) -> i64 {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::CoverageGaps, &QueryType::INSERT);
+        conn.execute(&sql, params![kind.to_string(), host.to_string(), start, end, detail])
+            .expect("Failed to execute query");
+        conn.last_insert_rowid()
+    }
+
+    pub fn close_coverage_gap(&self, id: i64, end: DateTime<Utc>) {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::CoverageGaps, &QueryType::UPDATE);
+        conn.execute(&sql, params![id, end]).expect("Failed to execute query");
+    }
+
+    /// Close every gap that is still open, i.e. when the daemon stops observing.
+    pub fn close_open_coverage_gaps(&self, end: DateTime<Utc>) {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_named_sql(&AWTables::CoverageGaps, &QueryType::UPDATE, "open");
+        conn.execute(&sql, params![end]).expect("Failed to execute query");
+    }
+
+    /// Select the gaps that overlap `[start, end)`, ordered by their start.
+    /// Open gaps are reported as ending `now`.
+    pub fn select_coverage_gaps(&self, start: DateTime<Utc>, end: DateTime<Utc>, now: DateTime<Utc>) -> Vec<CoverageGap> {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::CoverageGaps, &QueryType::SELECT);
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let results = stmt.query_map(params![start, end], |row| {
+            let gap_end: Option<DateTime<Utc>> = row.get(3)?;
+            Ok(CoverageGap {
+                kind: row.get::<usize, String>(0)?.parse().unwrap(),
+                host: row.get::<usize, String>(1)?.parse().unwrap(),
+                start: row.get(2)?,
+                end: gap_end.unwrap_or(now),
+                ongoing: gap_end.is_none(),
+                detail: row.get(4)?,
+            })
+        }).unwrap();
+
+        results.into_iter().map(|v| v.unwrap()).collect()
+    }
+
+    pub fn insert_unlock_event(&self, event: &UnlockEvent) {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::UnlockEvents, &QueryType::INSERT);
+        conn.execute(&sql, params![event.timestamp, event.method.to_string(), event.reason])
+            .expect("Failed to execute query");
+    }
+
+    /// Select the unlock events within `[start, end)`, ordered by time.
+    pub fn select_unlock_events(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<UnlockEvent> {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::UnlockEvents, &QueryType::SELECT);
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let results = stmt.query_map(params![start, end], |row| {
+            Ok(UnlockEvent {
+                timestamp: row.get(0)?,
+                method: row.get::<usize, String>(1)?.parse().unwrap(),
+                reason: row.get(2)?,
+            })
+        }).unwrap();
+
+        results.into_iter().map(|v| v.unwrap()).collect()
+    }
     
+    /// Queue a change until `apply_at`. Returns the identifier of the change.
+    pub fn insert_pending_change(&self, change: &Change, requested_at: DateTime<Utc>, apply_at: DateTime<Utc>) -> i64 {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::PendingChanges, &QueryType::INSERT);
+        let change = serde_json::to_string(change).unwrap();
+        conn.execute(&sql, params![change, requested_at, apply_at])
+            .expect("Failed to execute query");
+        conn.last_insert_rowid()
+    }
+
+    /// Select every pending change, ordered by the moment at which it applies.
+    pub fn select_pending_changes(&self) -> Vec<PendingChange> {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::PendingChanges, &QueryType::SELECT);
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let results = stmt.query_map([], |row| {
+            Ok(PendingChange {
+                id: row.get(0)?,
+                change: serde_json::from_str(&row.get::<usize, String>(1)?).unwrap(),
+                requested_at: row.get(2)?,
+                apply_at: row.get(3)?,
+            })
+        }).unwrap();
+
+        results.into_iter().map(|v| v.unwrap()).collect()
+    }
+
+    /// Remove a pending change. Returns whether the change existed.
+    pub fn delete_pending_change(&self, id: i64) -> bool {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::PendingChanges, &QueryType::DELETE);
+        conn.execute(&sql, params![id]).expect("Failed to execute query") > 0
+    }
+
     pub fn modify_timer(&self, action: QueryType, timer: Timer) {
         let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
         let sql = self.load_sql(&AWTables::Timers, &action);
         
-        let mut allowed_days = 0;
-        for (i, &day) in timer.allowed_days.iter().enumerate() {
-            if day {
-                allowed_days |= 1 << i;
-            }
-        }
-
         conn.execute(
             &sql, 
-            params![timer.display_name, timer.host.to_string(), timer.time_limit, allowed_days]
+            params![timer.display_name, timer.host.to_string(), timer.time_limit, encode_allowed_days(&timer)]
         ).expect("Failed to execute query");
     }
 
+    /// Insert a timer unless one already exists for its display name and host.
+    /// Returns whether the timer was written.
+    pub fn insert_timer_if_absent(&self, timer: &Timer) -> bool {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        let sql = self.load_named_sql(&AWTables::Timers, &QueryType::INSERT, "ignore");
+
+        let changed = conn.execute(
+            &sql, 
+            params![timer.display_name, timer.host.to_string(), timer.time_limit, encode_allowed_days(timer)]
+        ).expect("Failed to execute query");
+
+        changed > 0
+    }
+
     pub fn select_timers(&self) -> Vec<Timer> {
         debug!("Attempting to select all timers.");
 
@@ -159,20 +421,35 @@ impl PersistenceLayer {
         results.into_iter().map(|v| v.unwrap()).collect()
     }
 
-    pub fn select_current_durations(&self) -> DurationMap {
+    /// Sum the time spent per display name on the tracking day `today`, which
+    /// starts `day_start_hour` hours after local midnight.
+    pub fn select_current_durations(&self, today: NaiveDate, day_start_hour: u32) -> DurationMap {
+        // Query using the local timezone to align with user expectations.
+        let start = day::day_start(today, day_start_hour);
+        let end = day::day_start(today + chrono::Duration::days(1), day_start_hour);
+        self.select_durations(start, end, day_start_hour)
+    }
+
+    /// Sum the time spent per display name within `[start, end)`. Days that have
+    /// been rolled up into daily summaries are counted in full if they begin within the range.
+    pub fn select_durations(&self, start: DateTime<Utc>, end: DateTime<Utc>, day_start_hour: u32) -> DurationMap {
         let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
         
         let sql = self.load_sql(&AWTables::FocusChanges, &QueryType::SELECT);
         let mut stmt = conn.prepare(&sql).unwrap();
-        
-        // Query using the local timezone to align with user expectations.
-        let start_day = Local::now()
-            .duration_trunc(TimeDelta::days(1))
-            .unwrap();
-        let end_day = start_day + chrono::Duration::days(1);
-        let mut rows = stmt.query([
-            start_day,
+
+        let start_day = day::tracking_day(start, day_start_hour);
+        let end_day = day::tracking_day(end, day_start_hour);
+        let end_day = if end == day::day_start(end_day, day_start_hour) {
             end_day
+        } else {
+            end_day + chrono::Duration::days(1)
+        };
+        let mut rows = stmt.query(params![
+            start,
+            end,
+            start_day,
+            end_day,
         ]).unwrap();
         let mut durations: DurationMap = HashMap::new();
 
@@ -198,4 +475,233 @@ impl PersistenceLayer {
             params![fc.display_name, fc.host.to_string(), fc.timestamp, fc.duration]
         ).expect("Failed to execute query");
     }
-}
\ No newline at end of file
+
+    /// Replace the checkpoint of the sessions in progress. Each session is
+    /// stored as the focus change that it would record at the checkpoint.
+    pub fn checkpoint_active_sessions(&self, sessions: &[FocusChange]) {
+        let mut conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        let delete_sql = self.load_named_sql(&AWTables::ActiveSessions, &QueryType::DELETE, "all");
+        let insert_sql = self.load_sql(&AWTables::ActiveSessions, &QueryType::INSERT);
+
+        let tx = conn.transaction().expect("Failed to open a transaction");
+        tx.execute(&delete_sql, ()).expect("Failed to execute query");
+        {
+            let mut stmt = tx.prepare(&insert_sql).unwrap();
+            for fc in sessions {
+                stmt.execute(params![fc.display_name, fc.host.to_string(), fc.timestamp, fc.duration])
+                    .expect("Failed to execute query");
+            }
+        }
+        tx.commit().expect("Failed to commit the transaction");
+    }
+
+    /// Remove the checkpoint of a session that has been recorded.
+    pub fn delete_active_session(&self, host: &Host) {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        let sql = self.load_sql(&AWTables::ActiveSessions, &QueryType::DELETE);
+        conn.execute(&sql, params![host.to_string()]).expect("Failed to execute query");
+    }
+
+    /// Record the checkpointed sessions as focus changes and clear the checkpoint,
+    /// so that the sessions of a daemon that stopped unexpectedly are not lost.
+    pub fn recover_active_sessions(&self) -> Vec<FocusChange> {
+        let mut conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        let select_sql = self.load_sql(&AWTables::ActiveSessions, &QueryType::SELECT);
+        let delete_sql = self.load_named_sql(&AWTables::ActiveSessions, &QueryType::DELETE, "all");
+        let insert_sql = self.load_named_sql(&AWTables::FocusChanges, &QueryType::INSERT, "ignore");
+
+        let tx = conn.transaction().expect("Failed to open a transaction");
+        let sessions = {
+            let mut stmt = tx.prepare(&select_sql).unwrap();
+            let results = stmt.query_map([], |row| {
+                Ok(FocusChange {
+                    display_name: row.get(0)?,
+                    host: row.get::<usize, String>(1)?.parse().unwrap(),
+                    timestamp: row.get(2)?,
+                    duration: row.get(3)?,
+                })
+            }).unwrap();
+            results.into_iter().map(|v| v.unwrap()).collect::<Vec<FocusChange>>()
+        };
+        {
+            let mut stmt = tx.prepare(&insert_sql).unwrap();
+            for fc in &sessions {
+                stmt.execute(params![fc.display_name, fc.host.to_string(), fc.timestamp, fc.duration])
+                    .expect("Failed to execute query");
+            }
+        }
+        tx.execute(&delete_sql, ()).expect("Failed to execute query");
+        tx.commit().expect("Failed to commit the transaction");
+
+        sessions
+    }
+
+    /// Select the raw sessions that ended within `[start, end)`, ordered by time.
+    /// A negative `limit` returns every remaining session after `offset`.
+    pub fn select_sessions(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        limit: i64,
+        offset: i64,
+    ) -> Vec<FocusChange> {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_named_sql(&AWTables::FocusChanges, &QueryType::SELECT, "sessions");
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let results = stmt.query_map(params![start, end, limit, offset], |row| {
+            Ok(FocusChange {
+                display_name: row.get(0)?,
+                host: row.get::<usize, String>(1)?.parse().unwrap(),
+                timestamp: row.get(2)?,
+                duration: row.get(3)?,
+            })
+        }).unwrap();
+
+        results.into_iter().map(|v| v.unwrap()).collect()
+    }
+
+    /// Count the raw sessions that ended within `[start, end)`.
+    pub fn count_sessions(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> u32 {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_named_sql(&AWTables::FocusChanges, &QueryType::SELECT, "sessions_count");
+        conn.query_row(&sql, params![start, end], |row| row.get(0))
+            .expect("Failed to execute query")
+    }
+
+    /// Select every stored session, ordered by time.
+    pub fn select_all_focus_changes(&self) -> Vec<FocusChange> {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_named_sql(&AWTables::FocusChanges, &QueryType::SELECT, "all");
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let results = stmt.query_map([], |row| {
+            Ok(FocusChange {
+                display_name: row.get(0)?,
+                host: row.get::<usize, String>(1)?.parse().unwrap(),
+                timestamp: row.get(2)?,
+                duration: row.get(3)?,
+            })
+        }).unwrap();
+
+        results.into_iter().map(|v| v.unwrap()).collect()
+    }
+
+    /// Insert a batch of focus changes within a single transaction. Rows that
+    /// collide with the `(display_name, host, ts)` key are left untouched.
+    /// Returns the number of newly written rows.
+    pub fn insert_focus_changes_if_absent(&self, focus_changes: &[FocusChange]) -> u32 {
+        let mut conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        let sql = self.load_named_sql(&AWTables::FocusChanges, &QueryType::INSERT, "ignore");
+
+        let tx = conn.transaction().expect("Failed to open a transaction");
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(&sql).unwrap();
+            for fc in focus_changes {
+                inserted += stmt.execute(
+                    params![fc.display_name, fc.host.to_string(), fc.timestamp, fc.duration]
+                ).expect("Failed to execute query");
+            }
+        }
+        tx.commit().expect("Failed to commit the transaction");
+
+        inserted as u32
+    }
+
+    /// Select every daily summary, ordered by day.
+    pub fn select_daily_summaries(&self) -> Vec<DailySummary> {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+
+        let sql = self.load_sql(&AWTables::DailySummaries, &QueryType::SELECT);
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let results = stmt.query_map([], |row| {
+            Ok(DailySummary {
+                display_name: row.get(0)?,
+                host: row.get::<usize, String>(1)?.parse().unwrap(),
+                day: row.get(2)?,
+                duration: row.get(3)?,
+            })
+        }).unwrap();
+
+        results.into_iter().map(|v| v.unwrap()).collect()
+    }
+
+    /// Insert a batch of daily summaries within a single transaction. Summaries that
+    /// collide with the `(display_name, host, day)` key are left untouched.
+    /// Returns the number of newly written rows.
+    pub fn insert_daily_summaries_if_absent(&self, summaries: &[DailySummary]) -> u32 {
+        let mut conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        let sql = self.load_named_sql(&AWTables::DailySummaries, &QueryType::INSERT, "ignore");
+
+        let tx = conn.transaction().expect("Failed to open a transaction");
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(&sql).unwrap();
+            for summary in summaries {
+                inserted += stmt.execute(
+                    params![summary.display_name, summary.host.to_string(), summary.day, summary.duration]
+                ).expect("Failed to execute query");
+            }
+        }
+        tx.commit().expect("Failed to commit the transaction");
+
+        inserted as u32
+    }
+
+    /// Roll every raw session that ended before `cutoff` into the daily summaries,
+    /// keyed by the tracking day on which it ended. Returns the number of rolled up sessions.
+    pub fn compact_focus_changes(&self, cutoff: DateTime<Utc>, day_start_hour: u32) -> u32 {
+        let mut conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        let select_sql = self.load_named_sql(&AWTables::FocusChanges, &QueryType::SELECT, "before");
+        let insert_sql = self.load_sql(&AWTables::DailySummaries, &QueryType::INSERT);
+        let delete_sql = self.load_sql(&AWTables::FocusChanges, &QueryType::DELETE);
+
+        let tx = conn.transaction().expect("Failed to open a transaction");
+        let mut daily: HashMap<(String, String, NaiveDate), u32> = HashMap::new();
+        {
+            let mut stmt = tx.prepare(&select_sql).unwrap();
+            let mut rows = stmt.query([cutoff]).unwrap();
+            while let Some(row) = rows.next().unwrap() {
+                let display_name: String = row.get(0).unwrap();
+                let host: String = row.get(1).unwrap();
+                let ts: DateTime<Utc> = row.get(2).unwrap();
+                let dur: u32 = row.get(3).unwrap();
+
+                let day = day::tracking_day(ts, day_start_hour);
+                *daily.entry((display_name, host, day)).or_default() += dur;
+            }
+
+            let mut stmt = tx.prepare(&insert_sql).unwrap();
+            for ((display_name, host, day), dur) in daily.iter() {
+                stmt.execute(params![display_name, host, day, dur])
+                    .expect("Failed to execute query");
+            }
+        }
+        let removed = tx.execute(&delete_sql, [cutoff]).expect("Failed to execute query");
+        tx.commit().expect("Failed to commit the transaction");
+
+        info!("[RETENTION] Rolled {} sessions into {} daily summaries.", removed, daily.len());
+        removed as u32
+    }
+
+    /// Rebuild the database file to reclaim the space of deleted rows.
+    pub fn vacuum(&self) {
+        let conn = self.pool.get().expect("Failed to obtain SQLite connection.");
+        conn.execute("VACUUM", ()).expect("Failed to vacuum the database");
+        info!("[RETENTION] Vacuumed the database.");
+    }
+}
+
+/// Pack the weekly schedule of a timer into the bitmask stored in the DB.
+fn encode_allowed_days(timer: &Timer) -> u8 {
+    let mut allowed_days = 0;
+    for (i, &day) in timer.allowed_days.iter().enumerate() {
+        if day {
+            allowed_days |= 1 << i;
+        }
+    }
+
+    allowed_days
+}
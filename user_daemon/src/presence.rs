@@ -0,0 +1,92 @@
+/// A change of whether the user can be at the computer at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PresenceChange {
+    /// The system went to sleep, the screen locked or the user went idle.
+    /// The sessions in progress end.
+    Away,
+    /// The last of those ended. The watchers are asked for their focus,
+    /// which reopens the sessions.
+    Returned,
+}
+
+/// Follows the suspends, screen locks and idle periods, which overlap freely,
+/// i.e. the screen usually locks before a suspend and stays locked after the resume.
+/// The user only returns once all of them ended.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Presence {
+    asleep: bool,
+    locked: bool,
+    idle: bool,
+}
+
+impl Presence {
+    /// Whether the user is away, in which case no session should be open.
+    pub fn is_away(&self) -> bool {
+        self.asleep || self.locked || self.idle
+    }
+
+    /// The system is about to sleep if `start` is set, or resumed otherwise.
+    pub fn on_sleep(&mut self, start: bool) -> Option<PresenceChange> {
+        self.update(|p| p.asleep = start)
+    }
+
+    /// The screen saver became active if `active` is set, or was dismissed otherwise.
+    pub fn on_screen_lock(&mut self, active: bool) -> Option<PresenceChange> {
+        self.update(|p| p.locked = active)
+    }
+
+    /// The user passed the idle threshold if `idle` is set, or provided input again otherwise.
+    pub fn on_idle(&mut self, idle: bool) -> Option<PresenceChange> {
+        self.update(|p| p.idle = idle)
+    }
+
+    fn update(&mut self, apply: impl FnOnce(&mut Self)) -> Option<PresenceChange> {
+        let was_away = self.is_away();
+        apply(self);
+        match (was_away, self.is_away()) {
+            (false, true) => Some(PresenceChange::Away),
+            (true, false) => Some(PresenceChange::Returned),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_suspend_pauses_until_the_resume() {
+        let mut presence = Presence::default();
+        assert_eq!(presence.on_sleep(true), Some(PresenceChange::Away));
+        assert_eq!(presence.on_sleep(false), Some(PresenceChange::Returned));
+        assert!(!presence.is_away());
+    }
+
+    #[test]
+    fn a_resume_onto_the_lock_screen_waits_for_the_unlock() {
+        let mut presence = Presence::default();
+        assert_eq!(presence.on_screen_lock(true), Some(PresenceChange::Away));
+        assert_eq!(presence.on_sleep(true), None);
+        assert_eq!(presence.on_sleep(false), None);
+        assert!(presence.is_away());
+        assert_eq!(presence.on_screen_lock(false), Some(PresenceChange::Returned));
+    }
+
+    #[test]
+    fn repeated_signals_change_nothing() {
+        let mut presence = Presence::default();
+        assert_eq!(presence.on_screen_lock(false), None);
+        assert_eq!(presence.on_screen_lock(true), Some(PresenceChange::Away));
+        assert_eq!(presence.on_screen_lock(true), None);
+    }
+
+    #[test]
+    fn returning_from_idle_while_locked_keeps_the_user_away() {
+        let mut presence = Presence::default();
+        assert_eq!(presence.on_idle(true), Some(PresenceChange::Away));
+        assert_eq!(presence.on_screen_lock(true), None);
+        assert_eq!(presence.on_idle(false), None);
+        assert_eq!(presence.on_screen_lock(false), Some(PresenceChange::Returned));
+    }
+}
@@ -1,8 +1,10 @@
+use std::collections::HashMap;
 use zbus::proxy;
+use zbus::zvariant::{OwnedObjectPath, Value};
 
 #[proxy(interface="com.activity_warden.Watcher")]
 pub trait FirefoxWatcher {
-    async fn request_close(&self, metadata: &String) -> zbus::fdo::Result<()>;
+    async fn request_close(&self, tab_id: i64) -> zbus::fdo::Result<()>;
 }
 
 #[proxy(
@@ -15,6 +17,51 @@ pub trait ScreenSaver {
     fn ActiveChanged(active: bool);
 }
 
+/// The screen saver of other desktops, i.e. KDE, whose signal shares its generated types with GNOME's.
+pub mod freedesktop {
+    use zbus::proxy;
+
+    #[proxy(
+        interface = "org.freedesktop.ScreenSaver",
+        default_service = "org.freedesktop.ScreenSaver",
+        default_path = "/org/freedesktop/ScreenSaver"
+    )]
+    pub trait ScreenSaver {
+        #[zbus(signal)]
+        fn ActiveChanged(active: bool);
+    }
+}
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+pub trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+}
+
+#[proxy(
+    interface = "org.gnome.Mutter.IdleMonitor",
+    default_service = "org.gnome.Mutter.IdleMonitor",
+    default_path = "/org/gnome/Mutter/IdleMonitor/Core"
+)]
+pub trait IdleMonitor {
+    /// The milliseconds since the last input of the user.
+    fn get_idletime(&self) -> zbus::Result<u64>;
+}
+
 #[proxy(
     interface="org.freedesktop.login1.Manager",
     default_service="org.freedesktop.login1",
@@ -25,4 +72,19 @@ pub trait SuspendListener {
     /// `start == false` => just resumed
     #[zbus(signal)]
     fn prepare_for_sleep(&self, start: bool) -> zbus::fdo::Result<()>;
+
+    /// The object of a session, where `auto` refers to the display session of the user.
+    fn get_session(&self, session_id: &str) -> zbus::Result<OwnedObjectPath>;
+}
+
+#[proxy(
+    interface="org.freedesktop.login1.Session",
+    default_service="org.freedesktop.login1",
+)]
+pub trait LoginSession {
+    /// The session was asked to lock its screen, i.e. by `loginctl lock-session`.
+    #[zbus(signal)]
+    fn lock(&self) -> zbus::fdo::Result<()>;
+    #[zbus(signal)]
+    fn unlock(&self) -> zbus::fdo::Result<()>;
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+use futures_lite::stream::{Boxed, StreamExt};
+use log::{info, warn};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use zbus::Connection;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+
+use crate::proxy::{freedesktop, LoginSessionProxy, ScreenSaverProxy, SuspendListenerProxy};
+
+/// The number of system signals that may wait for the daemon.
+const SIGNAL_CAPACITY: usize = 16;
+
+/// A change of the system that affects whether the user can be at the computer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemSignal {
+    /// The system is about to sleep if set, or just resumed otherwise.
+    Sleep(bool),
+    /// The screen locked if set, or was unlocked otherwise.
+    ScreenLock(bool),
+}
+
+/// An integration with the desktop or the system that reports `SystemSignal`s.
+/// Several sources may report the same change, which the `Presence` tolerates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SystemSource {
+    /// `PrepareForSleep` of logind on the system bus.
+    LogindSleep,
+    /// `Lock` and `Unlock` of the logind session, i.e. from `loginctl lock-session`.
+    LogindSession,
+    /// `ActiveChanged` of GNOME's screen saver.
+    GnomeScreenSaver,
+    /// `ActiveChanged` of `org.freedesktop.ScreenSaver`, provided by KDE and others.
+    FreedesktopScreenSaver,
+}
+
+impl SystemSource {
+    pub const ALL: [SystemSource; 4] = [
+        SystemSource::LogindSleep,
+        SystemSource::LogindSession,
+        SystemSource::GnomeScreenSaver,
+        SystemSource::FreedesktopScreenSaver,
+    ];
+
+    /// The bus name that must be owned for the source to report anything.
+    fn service(&self) -> &'static str {
+        match self {
+            SystemSource::LogindSleep | SystemSource::LogindSession => "org.freedesktop.login1",
+            SystemSource::GnomeScreenSaver => "org.gnome.ScreenSaver",
+            SystemSource::FreedesktopScreenSaver => "org.freedesktop.ScreenSaver",
+        }
+    }
+
+    fn on_system_bus(&self) -> bool {
+        matches!(self, SystemSource::LogindSleep | SystemSource::LogindSession)
+    }
+
+    /// Subscribe to the signals of the source.
+    async fn subscribe(&self, conn: &Connection) -> zbus::Result<Boxed<SystemSignal>> {
+        let stream = match self {
+            SystemSource::LogindSleep => {
+                let proxy = SuspendListenerProxy::new(conn).await?;
+                proxy.receive_prepare_for_sleep().await?
+                    .filter_map(|signal| signal.args().ok().map(|args| SystemSignal::Sleep(args.start)))
+                    .boxed()
+            },
+            SystemSource::LogindSession => {
+                let manager = SuspendListenerProxy::new(conn).await?;
+                let path = manager.get_session("auto").await?;
+                let proxy = LoginSessionProxy::builder(conn).path(path)?.build().await?;
+                let lock = proxy.receive_lock().await?.map(|_| SystemSignal::ScreenLock(true));
+                let unlock = proxy.receive_unlock().await?.map(|_| SystemSignal::ScreenLock(false));
+                lock.or(unlock).boxed()
+            },
+            SystemSource::GnomeScreenSaver => {
+                let proxy = ScreenSaverProxy::new(conn).await?;
+                proxy.receive_ActiveChanged().await?
+                    .filter_map(|signal| signal.args().ok().map(|args| SystemSignal::ScreenLock(args.active)))
+                    .boxed()
+            },
+            SystemSource::FreedesktopScreenSaver => {
+                let proxy = freedesktop::ScreenSaverProxy::new(conn).await?;
+                proxy.receive_ActiveChanged().await?
+                    .filter_map(|signal| signal.args().ok().map(|args| SystemSignal::ScreenLock(args.active)))
+                    .boxed()
+            },
+        };
+        Ok(stream)
+    }
+}
+
+/// Follow every source and forward their signals to the returned receiver. A source
+/// whose service is not running is picked up once it appears, and subscribed to again
+/// when it restarts. Without the system bus, the logind sources are skipped with a
+/// warning, so that the daemon also runs in containers, if without the respective pauses.
+pub async fn start(session_conn: &Connection) -> mpsc::Receiver<SystemSignal> {
+    let (sender, receiver) = mpsc::channel(SIGNAL_CAPACITY);
+    let system_conn = match Connection::system().await {
+        Ok(conn) => Some(conn),
+        Err(e) => {
+            warn!("[SOURCES] The system bus is unavailable: {}", e);
+            None
+        }
+    };
+
+    for source in SystemSource::ALL {
+        let conn = if source.on_system_bus() {
+            match &system_conn {
+                Some(conn) => conn.clone(),
+                None => {
+                    warn!("[SOURCES] Skipping {:?} without the system bus.", source);
+                    continue;
+                }
+            }
+        } else {
+            session_conn.clone()
+        };
+
+        tokio::spawn(follow(source, conn, sender.clone()));
+    }
+
+    receiver
+}
+
+/// Forward the signals of `source` for as long as the daemon runs, subscribing
+/// again whenever its service gains a new owner.
+async fn follow(source: SystemSource, conn: Connection, sender: mpsc::Sender<SystemSignal>) {
+    // Watch the owner before probing it, so that a service starting in between is not missed.
+    let owner_changes = match DBusProxy::new(&conn).await {
+        Ok(dbus) => dbus.receive_name_owner_changed_with_args(&[(0, source.service())]).await,
+        Err(e) => Err(e),
+    };
+    let mut owner_changes = match owner_changes {
+        Ok(stream) => stream,
+        Err(e) => {
+            warn!("[SOURCES] Skipping {:?}, as its service cannot be watched: {}", source, e);
+            return;
+        }
+    };
+
+    let mut forwarder = if has_owner(&conn, source.service()).await {
+        forward(source, &conn, &sender).await
+    } else {
+        warn!("[SOURCES] {} is not running, {:?} follows once it starts.", source.service(), source);
+        None
+    };
+
+    while let Some(signal) = owner_changes.next().await {
+        let Ok(args) = signal.args() else {
+            continue;
+        };
+        if let Some(task) = forwarder.take() {
+            task.abort();
+        }
+        if args.new_owner().is_some() {
+            info!("[SOURCES] {} started.", source.service());
+            forwarder = forward(source, &conn, &sender).await;
+        } else {
+            warn!("[SOURCES] {} stopped, {:?} reports nothing until it returns.", source.service(), source);
+        }
+    }
+}
+
+/// Subscribe to `source` and spawn the task that forwards its signals, unless it fails.
+async fn forward(
+    source: SystemSource,
+    conn: &Connection,
+    sender: &mpsc::Sender<SystemSignal>,
+) -> Option<JoinHandle<()>> {
+    let mut stream = match source.subscribe(conn).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            warn!("[SOURCES] Skipping {:?}: {}", source, e);
+            return None;
+        }
+    };
+
+    info!("[SOURCES] Following {:?}.", source);
+    let sender = sender.clone();
+    Some(tokio::spawn(async move {
+        while let Some(signal) = stream.next().await {
+            if sender.send(signal).await.is_err() {
+                break;
+            }
+        }
+        warn!("[SOURCES] {:?} stopped reporting.", source);
+    }))
+}
+
+/// Whether `service` currently has an owner on the bus of `conn`.
+async fn has_owner(conn: &Connection, service: &'static str) -> bool {
+    let Ok(dbus) = DBusProxy::new(conn).await else {
+        return false;
+    };
+    let Ok(name) = BusName::try_from(service) else {
+        return false;
+    };
+    dbus.name_has_owner(name).await.unwrap_or(false)
+}
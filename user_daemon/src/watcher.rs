@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::future::Future;
+use zbus::{Connection, Error, Result};
+
+use shared::dbus::{DBus, Host, Interface};
+use shared::types::Event;
+use crate::proxy::{FirefoxWatcherProxy, NotificationsProxy};
+
+/// The means to act on the display names overseen by the watchers.
+pub trait Watcher: Clone + Send + Sync + 'static {
+    /// Ask the watcher that sent `event` to close its display name.
+    fn request_close(&self, event: &Event) -> impl Future<Output = Result<()>> + Send;
+
+    /// Warn the user that the display name of `event` is blocked in `remaining` seconds.
+    fn warn(&self, event: &Event, remaining: u32) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// Reaches the watchers over the session bus.
+#[derive(Clone)]
+pub struct DBusWatchers {
+    session_conn: Connection,
+}
+
+impl DBusWatchers {
+    pub fn new(session_conn: Connection) -> Self {
+        DBusWatchers { session_conn }
+    }
+}
+
+impl Watcher for DBusWatchers {
+    async fn request_close(&self, event: &Event) -> Result<()> {
+        // Actually connect to the respective `Watcher` to close the display name.
+        match event.source {
+            Host::FirefoxWatcher => {
+                let proxy = FirefoxWatcherProxy::builder(&self.session_conn)
+                    .destination(DBus::host_name(&event.source))?
+                    .path(DBus::object_path(&Host::FirefoxWatcher, &Interface::Watcher))?
+                    .build()
+                    .await?;
+
+                let _ = proxy.request_close(event.metadata.tab_id).await;
+            },
+            ref host => {
+                return Err(Error::Failure(format!("{} does not support closing display names.", host)));
+            },
+        };
+
+        Ok(())
+    }
+
+    async fn warn(&self, event: &Event, remaining: u32) -> Result<()> {
+        // The warning is a desktop notification, independent of the watcher.
+        let minutes = remaining.div_ceil(60);
+        let body = if minutes == 1 {
+            "1 minute remains today.".to_string()
+        } else {
+            format!("{} minutes remain today.", minutes)
+        };
+
+        let proxy = NotificationsProxy::new(&self.session_conn).await?;
+        proxy.notify(
+            "Activity Warden",
+            0,
+            "appointment-soon-symbolic",
+            &format!("{} closes soon", event.display_name),
+            &body,
+            &[],
+            HashMap::new(),
+            -1,
+        ).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use shared::dbus::Host;
+use shared::types::EventType;
+use user_daemon::aggregation::AggregationPolicy;
+use user_daemon::config::DaemonConfig;
+use user_daemon::decision::DisplayNameAction;
+
+use common::{event_on, Harness};
+
+fn harness(aggregation: AggregationPolicy) -> Harness {
+    let mut config = DaemonConfig::default();
+    config.tracking.aggregation = aggregation;
+    Harness::with_config(config)
+}
+
+#[test]
+fn all_counts_both_hosts() {
+    let mut h = harness(AggregationPolicy::All);
+
+    h.focus_on(Host::FirefoxWatcher, "example.com");
+    h.focus_on(Host::GnomeExtension, "Firefox");
+    h.advance_minutes(10);
+    h.focus_lost_on(Host::FirefoxWatcher);
+    h.focus_lost_on(Host::GnomeExtension);
+
+    assert_eq!(h.recorded_on(Host::FirefoxWatcher, "example.com"), 10 * 60);
+    assert_eq!(h.recorded_on(Host::GnomeExtension, "Firefox"), 10 * 60);
+}
+
+#[test]
+fn foreground_pauses_the_host_in_the_background() {
+    let mut h = harness(AggregationPolicy::Foreground);
+    h.add_timer("example.com", 30);
+
+    h.focus_on(Host::FirefoxWatcher, "example.com");
+    h.advance_minutes(5);
+
+    // Focusing an application pauses the browser along with its timer.
+    let outcome = h.send(event_on(Host::GnomeExtension, EventType::FocusChange, "Editor"));
+    let paused = &outcome.actions[1];
+    assert_eq!(paused.0.display_name, "example.com");
+    assert_eq!(paused.1, DisplayNameAction::Ignore);
+    h.advance_minutes(10);
+
+    // Leaving the application resumes the browser with the remaining time.
+    let outcome = h.focus_lost_on(Host::GnomeExtension);
+    assert_eq!(outcome.actions[1].1, DisplayNameAction::Time(25 * 60));
+    h.advance_minutes(5);
+    h.focus_lost_on(Host::FirefoxWatcher);
+
+    assert_eq!(h.recorded_on(Host::FirefoxWatcher, "example.com"), 10 * 60);
+    assert_eq!(h.recorded_on(Host::GnomeExtension, "Editor"), 10 * 60);
+}
+
+#[test]
+fn most_specific_prefers_the_browser_tab() {
+    let mut h = harness(AggregationPolicy::MostSpecific);
+
+    h.focus_on(Host::GnomeExtension, "Firefox");
+    h.focus_on(Host::FirefoxWatcher, "example.com");
+    h.advance_minutes(10);
+    h.focus_lost_on(Host::FirefoxWatcher);
+    h.advance_minutes(5);
+    h.focus_lost_on(Host::GnomeExtension);
+
+    assert_eq!(h.recorded_on(Host::FirefoxWatcher, "example.com"), 10 * 60);
+    assert_eq!(h.recorded_on(Host::GnomeExtension, "Firefox"), 5 * 60);
+}
+
+#[test]
+fn a_paused_host_is_not_timed() {
+    let mut h = harness(AggregationPolicy::MostSpecific);
+    h.add_timer("Firefox", 30);
+
+    h.focus_on(Host::FirefoxWatcher, "example.com");
+    assert_eq!(h.focus_on(Host::GnomeExtension, "Firefox"), DisplayNameAction::Ignore);
+}
+
+#[test]
+fn splitting_the_sessions_keeps_them_paused() {
+    let mut h = harness(AggregationPolicy::Foreground);
+
+    h.focus_on(Host::GnomeExtension, "Editor");
+    h.focus_on(Host::FirefoxWatcher, "example.com");
+    h.advance_minutes(10);
+    h.context.split_sessions();
+    h.advance_minutes(10);
+    h.focus_lost_on(Host::FirefoxWatcher);
+    h.focus_lost_on(Host::GnomeExtension);
+
+    assert_eq!(h.recorded_on(Host::FirefoxWatcher, "example.com"), 20 * 60);
+    assert_eq!(h.recorded_on(Host::GnomeExtension, "Editor"), 0);
+}
+
+#[test]
+fn active_sessions_report_the_remaining_time_and_whether_they_count() {
+    let mut h = harness(AggregationPolicy::MostSpecific);
+    h.add_timer("example.com", 30);
+
+    h.focus_on(Host::FirefoxWatcher, "example.com");
+    h.focus_on(Host::GnomeExtension, "Firefox");
+    h.advance_minutes(10);
+
+    let sessions = h.context.active_sessions();
+    assert_eq!(sessions.len(), 2);
+    let browser = sessions.iter().find(|s| s.host == Host::FirefoxWatcher).unwrap();
+    assert!(browser.counting && browser.has_timer);
+    assert_eq!(browser.remaining, 20 * 60);
+    let desktop = sessions.iter().find(|s| s.host == Host::GnomeExtension).unwrap();
+    assert!(!desktop.counting && !desktop.has_timer);
+}
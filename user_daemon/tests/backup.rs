@@ -0,0 +1,84 @@
+mod common;
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use shared::dbus::Host;
+use shared::types::backup::{Backup, BackupFormat};
+use shared::types::schema::FocusChange;
+use user_daemon::backup;
+
+use common::Harness;
+
+fn session(display_name: &str, timestamp: DateTime<Utc>, minutes: u32) -> FocusChange {
+    FocusChange {
+        host: Host::FirefoxWatcher,
+        display_name: display_name.to_string(),
+        timestamp,
+        duration: minutes * 60,
+    }
+}
+
+fn local(day: u32, hour: u32) -> DateTime<Utc> {
+    Local.with_ymd_and_hms(2025, 3, day, hour, 0, 0).unwrap().with_timezone(&Utc)
+}
+
+#[test]
+fn an_export_never_contains_the_password_hash() {
+    let h = Harness::new();
+    h.persistence().set_password_hash("$argon2id$v=19$m=19456,t=2,p=1$c2FsdA$aGFzaA");
+
+    let data = backup::export_data(h.persistence(), BackupFormat::Json).unwrap();
+    let exported: Backup = serde_json::from_str(&data).unwrap();
+    assert_eq!(exported.settings.password_hash, None);
+    assert!(!data.contains("argon2"));
+}
+
+#[test]
+fn an_export_contains_the_compacted_days() {
+    let h = Harness::new();
+    let pl = h.persistence();
+    pl.insert_focus_change(&session("example.com", local(1, 10), 10));
+    pl.insert_focus_change(&session("example.com", local(1, 11), 5));
+    pl.compact_focus_changes(local(2, 0), 0);
+
+    let data = backup::export_data(pl, BackupFormat::Json).unwrap();
+    let exported: Backup = serde_json::from_str(&data).unwrap();
+    assert!(exported.focus_changes.is_empty());
+    assert_eq!(exported.daily_summaries.len(), 1);
+    assert_eq!(exported.daily_summaries[0].duration, 15 * 60);
+}
+
+#[test]
+fn an_older_backup_does_not_count_a_compacted_day_twice() {
+    let h = Harness::new();
+    let pl = h.persistence();
+    pl.insert_focus_change(&session("example.com", local(1, 10), 10));
+    pl.insert_focus_change(&session("example.com", local(3, 10), 20));
+    let before_compaction = backup::export_data(pl, BackupFormat::Json).unwrap();
+
+    pl.compact_focus_changes(local(2, 0), 0);
+    let summary = backup::import_data(pl, BackupFormat::Json, &before_compaction, 0).unwrap();
+    assert_eq!(summary.focus_changes_imported, 0);
+    assert_eq!(summary.focus_changes_skipped, 2);
+
+    let durations = pl.select_durations(local(1, 0), local(4, 0), 0);
+    assert_eq!(durations[&Host::FirefoxWatcher]["example.com"], 30 * 60);
+}
+
+#[test]
+fn a_summary_is_not_imported_over_the_raw_sessions_of_its_day() {
+    let h = Harness::new();
+    let pl = h.persistence();
+    pl.insert_focus_change(&session("example.com", local(1, 10), 10));
+    let raw = backup::export_data(pl, BackupFormat::Json).unwrap();
+    pl.compact_focus_changes(local(2, 0), 0);
+    let compacted = backup::export_data(pl, BackupFormat::Json).unwrap();
+
+    let other = Harness::new();
+    backup::import_data(other.persistence(), BackupFormat::Json, &raw, 0).unwrap();
+    let summary = backup::import_data(other.persistence(), BackupFormat::Json, &compacted, 0).unwrap();
+    assert_eq!(summary.daily_summaries_imported, 0);
+    assert_eq!(summary.daily_summaries_skipped, 1);
+
+    let durations = other.persistence().select_durations(local(1, 0), local(2, 0), 0);
+    assert_eq!(durations[&Host::FirefoxWatcher]["example.com"], 10 * 60);
+}
@@ -0,0 +1,183 @@
+// Every test binary only uses a part of the harness.
+#![allow(dead_code)]
+
+use chrono::{DateTime, Local, TimeZone, Utc};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use shared::dbus::Host;
+use shared::types::{Event, EventMetadata, EventType};
+use shared::types::schema::Timer;
+use user_daemon::clock::{JumpPolicy, ManualClock, TrustedClock};
+use user_daemon::config::DaemonConfig;
+use user_daemon::context::DaemonContext;
+use user_daemon::decision::{self, DisplayNameAction, Outcome};
+use user_daemon::persistence::PersistenceLayer;
+use user_daemon::watcher::Watcher;
+
+/// The number of harnesses created so far, used to keep their files apart.
+static NEXT_HARNESS: AtomicUsize = AtomicUsize::new(0);
+
+/// A daemon core that runs against a manual clock and an in-memory database.
+pub struct Harness {
+    pub context: DaemonContext,
+    pub clock: Arc<ManualClock>,
+    config: DaemonConfig,
+    pl: PersistenceLayer,
+    data_root: PathBuf,
+}
+
+impl Harness {
+    /// Start at noon in the local timezone, far from either day boundary.
+    pub fn new() -> Self {
+        Self::with_config(DaemonConfig::default())
+    }
+
+    pub fn with_config(config: DaemonConfig) -> Self {
+        Self::build(Local.with_ymd_and_hms(2025, 3, 14, 12, 0, 0).unwrap().with_timezone(&Utc), config)
+    }
+
+    pub fn starting_at(start: DateTime<Utc>) -> Self {
+        Self::build(start, DaemonConfig::default())
+    }
+
+    pub fn build(start: DateTime<Utc>, config: DaemonConfig) -> Self {
+        let data_root = std::env::temp_dir().join(format!(
+            "activity_warden_test_{}_{}",
+            std::process::id(),
+            NEXT_HARNESS.fetch_add(1, Ordering::Relaxed),
+        ));
+        fs::create_dir_all(&data_root).unwrap();
+
+        let clock = ManualClock::new(start);
+        let pl = PersistenceLayer::in_memory(data_root.clone());
+        let context = DaemonContext::with_parts(
+            config.clone(),
+            TrustedClock::new(clock.clone(), JumpPolicy::Monotonic),
+            pl.clone(),
+        );
+        Harness { context, clock, config, pl, data_root }
+    }
+
+    /// Replace the context with a new one on the same database, as if the
+    /// daemon stopped without a chance to record its state.
+    pub fn crash_and_restart(&mut self) {
+        self.context = DaemonContext::with_parts(
+            self.config.clone(),
+            TrustedClock::new(self.clock.clone(), JumpPolicy::Monotonic),
+            self.pl.clone(),
+        );
+    }
+
+    /// The database shared by every context of the harness.
+    pub fn persistence(&self) -> &PersistenceLayer {
+        &self.pl
+    }
+
+    /// Replace the context with a new one on the same database that reads `config`,
+    /// as if the configuration file was edited while the daemon was stopped.
+    pub fn restart_with_config(&mut self, config: DaemonConfig) {
+        self.config = config;
+        self.crash_and_restart();
+    }
+
+    /// Allow `minutes` on `display_name` on every day of the week.
+    pub fn add_timer(&self, display_name: &str, minutes: u32) {
+        self.context.add_timer(Timer {
+            display_name: display_name.to_string(),
+            host: Host::FirefoxWatcher,
+            time_limit: minutes * 60,
+            allowed_days: vec![true; 7],
+        });
+    }
+
+    pub fn advance_minutes(&self, minutes: i64) {
+        self.clock.advance(minutes * 60);
+    }
+
+    /// Switch the browser to `display_name`.
+    pub fn focus(&mut self, display_name: &str) -> DisplayNameAction {
+        self.focus_on(Host::FirefoxWatcher, display_name)
+    }
+
+    /// Switch `host` to `display_name`.
+    pub fn focus_on(&mut self, host: Host, display_name: &str) -> DisplayNameAction {
+        self.send(event_on(host, EventType::FocusChange, display_name)).actions[0].1
+    }
+
+    /// Leave the browser without focusing another display name.
+    pub fn focus_lost(&mut self) {
+        self.focus_lost_on(Host::FirefoxWatcher);
+    }
+
+    pub fn focus_lost_on(&mut self, host: Host) -> Outcome {
+        self.send(event_on(host, EventType::FocusLost, ""))
+    }
+
+    pub fn send(&mut self, event: Event) -> Outcome {
+        decision::process_event(&mut self.context, &event)
+    }
+
+    /// The seconds recorded on `display_name` today, excluding the current session.
+    pub fn recorded(&self, display_name: &str) -> u32 {
+        self.recorded_on(Host::FirefoxWatcher, display_name)
+    }
+
+    pub fn recorded_on(&self, host: Host, display_name: &str) -> u32 {
+        self.context.timer_durations.get(&host)
+            .and_then(|durations| durations.get(display_name))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.data_root);
+    }
+}
+
+pub fn event(event_type: EventType, display_name: &str) -> Event {
+    event_on(Host::FirefoxWatcher, event_type, display_name)
+}
+
+pub fn event_on(host: Host, event_type: EventType, display_name: &str) -> Event {
+    Event {
+        event_type,
+        source: host,
+        display_name: display_name.to_string(),
+        metadata: EventMetadata::default(),
+    }
+}
+
+/// A watcher that remembers the display names it was asked to close.
+#[derive(Clone, Default)]
+pub struct FakeWatcher {
+    closed: Arc<Mutex<Vec<String>>>,
+    warned: Arc<Mutex<Vec<(String, u32)>>>,
+}
+
+impl FakeWatcher {
+    pub fn closed(&self) -> Vec<String> {
+        self.closed.lock().unwrap().clone()
+    }
+
+    /// The display names that the user was warned about, with the seconds that remained.
+    pub fn warned(&self) -> Vec<(String, u32)> {
+        self.warned.lock().unwrap().clone()
+    }
+}
+
+impl Watcher for FakeWatcher {
+    async fn request_close(&self, event: &Event) -> zbus::Result<()> {
+        self.closed.lock().unwrap().push(event.display_name.clone());
+        Ok(())
+    }
+
+    async fn warn(&self, event: &Event, remaining: u32) -> zbus::Result<()> {
+        self.warned.lock().unwrap().push((event.display_name.clone(), remaining));
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use std::time::Duration;
+use shared::dbus::Host;
+use shared::types::EventType;
+use user_daemon::decision::DisplayNameAction;
+use user_daemon::enforcement::Enforcer;
+
+use common::{event_on, FakeWatcher, Harness};
+
+fn focus(host: Host, display_name: &str) -> shared::types::Event {
+    event_on(host, EventType::FocusChange, display_name)
+}
+
+#[tokio::test(start_paused = true)]
+async fn hosts_are_enforced_independently() {
+    let watcher = FakeWatcher::default();
+    let mut enforcer = Enforcer::new(watcher.clone());
+
+    enforcer.handle(&focus(Host::FirefoxWatcher, "example.com"), DisplayNameAction::Time(60));
+    enforcer.handle(&focus(Host::GnomeExtension, "Editor"), DisplayNameAction::Time(120));
+
+    tokio::time::sleep(Duration::from_secs(61)).await;
+    assert_eq!(watcher.closed(), vec!["example.com".to_string()]);
+    assert_eq!(enforcer.pending(&Host::GnomeExtension), Some("Editor"));
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(watcher.closed(), vec!["example.com".to_string(), "Editor".to_string()]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_new_display_name_replaces_the_timer_of_its_host() {
+    let watcher = FakeWatcher::default();
+    let mut enforcer = Enforcer::new(watcher.clone());
+
+    enforcer.handle(&focus(Host::FirefoxWatcher, "example.com"), DisplayNameAction::Time(60));
+    enforcer.handle(&focus(Host::FirefoxWatcher, "other.org"), DisplayNameAction::Ignore);
+    assert_eq!(enforcer.pending(&Host::FirefoxWatcher), None);
+
+    tokio::time::sleep(Duration::from_secs(120)).await;
+    assert!(watcher.closed().is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn heartbeats_and_other_hosts_leave_a_timer_running() {
+    let watcher = FakeWatcher::default();
+    let mut enforcer = Enforcer::new(watcher.clone());
+
+    enforcer.handle(&focus(Host::FirefoxWatcher, "example.com"), DisplayNameAction::Time(60));
+    enforcer.handle(&event_on(Host::FirefoxWatcher, EventType::Heartbeat, ""), DisplayNameAction::Ignore);
+    enforcer.handle(&event_on(Host::GnomeExtension, EventType::FocusLost, ""), DisplayNameAction::Ignore);
+
+    tokio::time::sleep(Duration::from_secs(61)).await;
+    assert_eq!(watcher.closed(), vec!["example.com".to_string()]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancelling_all_timers_stops_every_block() {
+    let watcher = FakeWatcher::default();
+    let mut enforcer = Enforcer::new(watcher.clone());
+
+    enforcer.handle(&focus(Host::FirefoxWatcher, "example.com"), DisplayNameAction::Time(60));
+    enforcer.handle(&focus(Host::GnomeExtension, "Editor"), DisplayNameAction::Time(60));
+    enforcer.cancel_all();
+
+    tokio::time::sleep(Duration::from_secs(120)).await;
+    assert!(watcher.closed().is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn a_browser_tab_and_an_application_count_down_together() {
+    let mut h = Harness::new();
+    h.add_timer("example.com", 30);
+    h.add_timer("Editor", 10);
+    let watcher = FakeWatcher::default();
+    let mut enforcer = Enforcer::new(watcher.clone());
+
+    for event in [focus(Host::FirefoxWatcher, "example.com"), focus(Host::GnomeExtension, "Editor")] {
+        for (event, action) in h.send(event).actions {
+            enforcer.handle(&event, action);
+        }
+    }
+    assert_eq!(enforcer.pending(&Host::FirefoxWatcher), Some("example.com"));
+    assert_eq!(enforcer.pending(&Host::GnomeExtension), Some("Editor"));
+
+    tokio::time::sleep(Duration::from_secs(10 * 60 + 1)).await;
+    assert_eq!(watcher.closed(), vec!["Editor".to_string()]);
+    assert_eq!(enforcer.pending(&Host::FirefoxWatcher), Some("example.com"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_user_is_warned_before_the_block() {
+    let watcher = FakeWatcher::default();
+    let mut enforcer = Enforcer::new(watcher.clone());
+    enforcer.set_warnings(&[1, 5]);
+
+    enforcer.handle(&focus(Host::FirefoxWatcher, "example.com"), DisplayNameAction::Time(10 * 60));
+
+    tokio::time::sleep(Duration::from_secs(5 * 60 + 1)).await;
+    assert_eq!(watcher.warned(), vec![("example.com".to_string(), 5 * 60)]);
+
+    tokio::time::sleep(Duration::from_secs(4 * 60)).await;
+    assert_eq!(watcher.warned().last(), Some(&("example.com".to_string(), 60)));
+    assert!(watcher.closed().is_empty());
+
+    tokio::time::sleep(Duration::from_secs(60)).await;
+    assert_eq!(watcher.closed(), vec!["example.com".to_string()]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn warnings_that_are_already_due_are_skipped() {
+    let watcher = FakeWatcher::default();
+    let mut enforcer = Enforcer::new(watcher.clone());
+    enforcer.set_warnings(&[5, 1]);
+
+    enforcer.handle(&focus(Host::FirefoxWatcher, "example.com"), DisplayNameAction::Time(3 * 60));
+
+    tokio::time::sleep(Duration::from_secs(3 * 60 + 1)).await;
+    assert_eq!(watcher.warned(), vec![("example.com".to_string(), 60)]);
+    assert_eq!(watcher.closed(), vec!["example.com".to_string()]);
+}
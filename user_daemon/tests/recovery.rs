@@ -0,0 +1,90 @@
+mod common;
+
+use user_daemon::decision::DisplayNameAction;
+
+use common::Harness;
+
+#[test]
+fn a_crash_keeps_the_checkpointed_time() {
+    let mut h = Harness::new();
+    h.add_timer("example.com", 30);
+
+    h.focus("example.com");
+    h.advance_minutes(10);
+    h.context.checkpoint_sessions();
+    h.advance_minutes(1);
+    h.crash_and_restart();
+
+    // Only the time after the last checkpoint is lost.
+    assert_eq!(h.recorded("example.com"), 10 * 60);
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(20 * 60));
+}
+
+#[test]
+fn a_completed_session_is_not_recovered_again() {
+    let mut h = Harness::new();
+
+    h.focus("example.com");
+    h.advance_minutes(10);
+    h.context.checkpoint_sessions();
+    h.advance_minutes(5);
+    h.focus("other.org");
+    h.crash_and_restart();
+
+    assert_eq!(h.recorded("example.com"), 15 * 60);
+}
+
+#[test]
+fn the_checkpoint_is_only_recovered_once() {
+    let mut h = Harness::new();
+
+    h.focus("example.com");
+    h.advance_minutes(10);
+    h.context.checkpoint_sessions();
+    h.crash_and_restart();
+    h.crash_and_restart();
+
+    assert_eq!(h.recorded("example.com"), 10 * 60);
+}
+
+#[test]
+fn a_clean_shutdown_records_the_whole_session() {
+    let mut h = Harness::new();
+
+    h.focus("example.com");
+    h.advance_minutes(10);
+    h.context.clear_last_event();
+    h.context.checkpoint_sessions();
+    h.crash_and_restart();
+
+    assert_eq!(h.recorded("example.com"), 10 * 60);
+}
+
+#[test]
+fn a_shutdown_returns_the_sessions_it_completed() {
+    let mut h = Harness::new();
+
+    h.focus("example.com");
+    h.advance_minutes(10);
+    let completed = h.context.shutdown();
+    assert_eq!(completed.len(), 1);
+    assert_eq!(completed[0].display_name, "example.com");
+    assert_eq!(completed[0].duration, 10 * 60);
+
+    h.crash_and_restart();
+    assert_eq!(h.recorded("example.com"), 10 * 60);
+}
+
+#[test]
+fn a_restart_keeps_the_delay_after_failed_password_attempts() {
+    let mut h = Harness::new();
+    h.persistence().set_password_hash(&user_daemon::auth::hash_password("secret"));
+
+    for _ in 0..4 {
+        assert!(h.context.change_password("wrong".to_string(), "new".to_string()).is_err());
+    }
+    h.crash_and_restart();
+
+    let result = h.context.change_password("secret".to_string(), "new".to_string());
+    assert!(matches!(result, Err(zbus::fdo::Error::LimitsExceeded(_))));
+}
@@ -0,0 +1,368 @@
+mod common;
+
+use chrono::{Duration, Local, TimeZone, Utc};
+use shared::types::EventType;
+use shared::types::schema::Timer;
+use shared::dbus::Host;
+use shared::types::daemon::GapKind;
+use user_daemon::clock::Clock;
+use user_daemon::config::DaemonConfig;
+use user_daemon::decision::{self, DisplayNameAction};
+
+use common::{event, FakeWatcher, Harness};
+
+#[test]
+fn returning_to_a_display_name_blocks_it_at_the_limit() {
+    let mut h = Harness::new();
+    h.add_timer("example.com", 30);
+
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(30 * 60));
+    h.advance_minutes(29);
+    assert_eq!(h.focus("other.org"), DisplayNameAction::Ignore);
+    assert_eq!(h.recorded("example.com"), 29 * 60);
+
+    h.advance_minutes(10);
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(60));
+    h.advance_minutes(1);
+    assert_eq!(h.focus("other.org"), DisplayNameAction::Ignore);
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Block);
+}
+
+#[test]
+fn refocusing_the_same_display_name_counts_the_current_session() {
+    let mut h = Harness::new();
+    h.add_timer("example.com", 30);
+
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(30 * 60));
+    h.advance_minutes(20);
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(10 * 60));
+}
+
+#[test]
+fn a_blocked_display_name_accrues_no_time() {
+    let mut h = Harness::new();
+    h.add_timer("example.com", 0);
+
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Block);
+    h.advance_minutes(5);
+    h.focus("other.org");
+    assert_eq!(h.recorded("example.com"), 0);
+}
+
+#[test]
+fn a_timer_disabled_today_blocks_right_away() {
+    let mut h = Harness::new();
+    h.context.add_timer(Timer {
+        display_name: "example.com".to_string(),
+        host: Host::FirefoxWatcher,
+        time_limit: 60 * 60,
+        allowed_days: vec![false; 7],
+    });
+
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Block);
+}
+
+#[test]
+fn a_timer_is_only_added_with_7_days_and_once() {
+    let h = Harness::new();
+    h.add_timer("example.com", 30);
+
+    let timer = |display_name: &str, days: usize| Timer {
+        display_name: display_name.to_string(),
+        host: Host::FirefoxWatcher,
+        time_limit: 60,
+        allowed_days: vec![true; days],
+    };
+    assert!(h.context.check_new_timer(&timer("other.org", 7)).is_ok());
+    assert!(h.context.check_new_timer(&timer("other.org", 0)).is_err());
+    assert!(h.context.check_new_timer(&timer("other.org", 8)).is_err());
+    assert!(h.context.check_new_timer(&timer("example.com", 7)).is_err());
+}
+
+#[test]
+fn losing_focus_stops_the_session() {
+    let mut h = Harness::new();
+
+    h.focus("other.org");
+    h.advance_minutes(3);
+    h.focus_lost();
+    h.advance_minutes(60);
+    h.focus("other.org");
+    assert_eq!(h.recorded("other.org"), 3 * 60);
+}
+
+#[test]
+fn the_budget_resets_on_a_new_day() {
+    let late = Local.with_ymd_and_hms(2025, 3, 14, 23, 0, 0).unwrap().with_timezone(&Utc);
+    let mut h = Harness::starting_at(late);
+    h.add_timer("example.com", 30);
+
+    h.focus("example.com");
+    h.advance_minutes(29);
+    h.focus("other.org");
+    h.advance_minutes(2 * 60);
+
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(30 * 60));
+    assert_eq!(h.recorded("example.com"), 0);
+}
+
+#[test]
+fn the_night_counts_towards_the_previous_day() {
+    let mut config = DaemonConfig::default();
+    config.tracking.day_start_hour = 4;
+    let late = Local.with_ymd_and_hms(2025, 3, 14, 23, 0, 0).unwrap().with_timezone(&Utc);
+    let mut h = Harness::build(late, config);
+    h.add_timer("example.com", 30);
+
+    h.focus("example.com");
+    h.advance_minutes(29);
+    h.focus("other.org");
+    h.advance_minutes(2 * 60);
+
+    // At 1 AM, the day that started at 4 AM yesterday is still going.
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(60));
+    h.focus("other.org");
+    assert_eq!(h.recorded("example.com"), 29 * 60);
+
+    // At 4 AM, the budget resets.
+    h.advance_minutes(3 * 60);
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(30 * 60));
+    assert_eq!(h.recorded("example.com"), 0);
+}
+
+#[test]
+fn moving_the_day_start_keeps_the_time_recorded_on_the_new_day() {
+    let mut config = DaemonConfig::default();
+    config.tracking.day_start_hour = 4;
+    let night = Local.with_ymd_and_hms(2025, 3, 15, 0, 20, 0).unwrap().with_timezone(&Utc);
+    let mut h = Harness::build(night, config.clone());
+    h.add_timer("example.com", 30);
+
+    h.focus("example.com");
+    h.advance_minutes(29);
+    h.focus("other.org");
+    h.advance_minutes(11);
+
+    // At 1 AM, the time since midnight falls on the day that now starts at midnight.
+    config.tracking.day_start_hour = 0;
+    h.context.apply_config(config, true);
+    assert_eq!(h.recorded("example.com"), 29 * 60);
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(60));
+}
+
+/// The seconds stored on `display_name` for the tracking day `days_ago` days before today.
+fn stored(h: &Harness, display_name: &str, days_ago: i64) -> u32 {
+    let day = h.context.today() - Duration::days(days_ago);
+    h.persistence().select_current_durations(day, 0)
+        .get(&Host::FirefoxWatcher)
+        .and_then(|durations| durations.get(display_name))
+        .copied()
+        .unwrap_or(0)
+}
+
+#[test]
+fn a_session_across_the_day_start_is_split_between_the_days() {
+    let late = Local.with_ymd_and_hms(2025, 3, 14, 23, 50, 0).unwrap().with_timezone(&Utc);
+    let mut h = Harness::starting_at(late);
+    h.add_timer("example.com", 30);
+
+    h.focus("example.com");
+    h.advance_minutes(10);
+    // The daemon wakes up at the day start.
+    assert!(h.context.reset_daily_state());
+    assert_eq!(h.recorded("example.com"), 0);
+    h.advance_minutes(20);
+    h.focus("other.org");
+
+    assert_eq!(h.recorded("example.com"), 20 * 60);
+    assert_eq!(stored(&h, "example.com", 0), 20 * 60);
+    assert_eq!(stored(&h, "example.com", 1), 10 * 60);
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(10 * 60));
+}
+
+#[test]
+fn a_missed_wakeup_still_splits_the_session_at_the_day_start() {
+    let late = Local.with_ymd_and_hms(2025, 3, 14, 23, 50, 0).unwrap().with_timezone(&Utc);
+    let mut h = Harness::starting_at(late);
+
+    h.focus("example.com");
+    h.advance_minutes(30);
+    h.focus("other.org");
+
+    assert_eq!(h.recorded("example.com"), 20 * 60);
+    assert_eq!(stored(&h, "example.com", 0), 20 * 60);
+    assert_eq!(stored(&h, "example.com", 1), 10 * 60);
+}
+
+#[test]
+fn setting_the_clock_forward_keeps_the_budget() {
+    let mut h = Harness::new();
+    h.add_timer("example.com", 30);
+
+    h.focus("example.com");
+    h.advance_minutes(29);
+    h.focus("other.org");
+
+    let tomorrow = h.clock.wall() + Duration::days(1);
+    h.clock.set_wall(tomorrow);
+    assert!(h.context.check_coverage());
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(60));
+}
+
+#[test]
+fn setting_the_clock_back_across_a_restart_keeps_the_day() {
+    let mut h = Harness::new();
+    h.add_timer("example.com", 30);
+    h.context.record_startup();
+
+    h.focus("example.com");
+    h.advance_minutes(29);
+    h.context.shutdown();
+
+    let yesterday = h.clock.wall() - Duration::days(1);
+    h.clock.set_wall(yesterday);
+    h.crash_and_restart();
+    h.context.record_startup();
+
+    let now = h.context.now();
+    let gaps = h.persistence().select_coverage_gaps(now - Duration::days(1), now + Duration::days(2), now);
+    assert!(gaps.iter().any(|gap| gap.kind == GapKind::ClockJump));
+    assert_eq!(h.focus("example.com"), DisplayNameAction::Time(60));
+}
+
+#[test]
+fn heartbeats_do_not_end_the_session() {
+    let mut h = Harness::new();
+
+    h.focus("other.org");
+    h.advance_minutes(2);
+    let outcome = h.send(event(EventType::Heartbeat, ""));
+    assert!(outcome.focus_change.is_none());
+    assert_eq!(outcome.actions[0].1, DisplayNameAction::Ignore);
+    assert_eq!(h.recorded("other.org"), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_watcher_is_asked_to_close_once_the_time_runs_out() {
+    let watcher = FakeWatcher::default();
+    let task = tokio::spawn(decision::block_display_name(
+        watcher.clone(),
+        event(EventType::FocusChange, "example.com"),
+        60,
+    ));
+
+    tokio::time::sleep(std::time::Duration::from_secs(59)).await;
+    assert!(watcher.closed().is_empty());
+
+    task.await.unwrap().unwrap();
+    assert_eq!(watcher.closed(), vec!["example.com".to_string()]);
+}
+
+#[test]
+fn idle_time_does_not_count() {
+    let mut h = Harness::new();
+
+    h.focus("example.com");
+    h.advance_minutes(20);
+    let completed = h.context.end_idle_sessions(5 * 60);
+
+    assert_eq!(completed.len(), 1);
+    assert_eq!(h.recorded("example.com"), 15 * 60);
+    assert!(h.context.last_event.is_empty());
+}
+
+fn with_cooling_off(minutes: u32) -> DaemonConfig {
+    let mut config = DaemonConfig::default();
+    config.lock.cooling_off_minutes = minutes;
+    config
+}
+
+#[test]
+fn shortening_the_cooling_off_period_waits_for_it() {
+    let mut h = Harness::with_config(with_cooling_off(60));
+
+    assert!(h.context.apply_config(with_cooling_off(0), true));
+    assert_eq!(h.context.config().lock.cooling_off_minutes, 60);
+    let pending = h.context.get_pending_changes();
+    assert!(pending.cooling_off_pending);
+    assert_eq!(pending.cooling_off_minutes, 0);
+    assert_eq!(pending.cooling_off_at, h.context.now() + Duration::minutes(60));
+
+    // Reloading the same file keeps the original schedule.
+    h.advance_minutes(30);
+    assert!(!h.context.apply_config(with_cooling_off(0), true));
+    assert_eq!(h.context.get_pending_changes().cooling_off_at, h.context.now() + Duration::minutes(30));
+
+    // Restoring the period withdraws the reduction.
+    assert!(h.context.apply_config(with_cooling_off(60), true));
+    assert!(!h.context.get_pending_changes().cooling_off_pending);
+}
+
+#[test]
+fn a_longer_cooling_off_period_applies_immediately() {
+    let mut h = Harness::with_config(with_cooling_off(10));
+
+    assert!(!h.context.apply_config(with_cooling_off(60), true));
+    assert_eq!(h.context.config().lock.cooling_off_minutes, 60);
+}
+
+#[test]
+fn a_restart_keeps_the_cooling_off_period_until_its_reduction_applies() {
+    let mut h = Harness::with_config(with_cooling_off(60));
+    h.context.apply_config(with_cooling_off(0), true);
+
+    h.restart_with_config(with_cooling_off(0));
+    assert_eq!(h.context.config().lock.cooling_off_minutes, 60);
+}
+
+#[test]
+fn a_shorter_cooling_off_period_in_the_file_waits_after_a_restart() {
+    let mut h = Harness::with_config(with_cooling_off(60));
+
+    // The file was edited while the daemon was stopped.
+    h.restart_with_config(with_cooling_off(0));
+    assert_eq!(h.context.config().lock.cooling_off_minutes, 60);
+    let pending = h.context.get_pending_changes();
+    assert!(pending.cooling_off_pending);
+    assert_eq!(pending.cooling_off_minutes, 0);
+}
+
+fn with_day_start(mut config: DaemonConfig, hour: u32) -> DaemonConfig {
+    config.tracking.day_start_hour = hour;
+    config
+}
+
+#[test]
+fn moving_the_day_start_waits_for_the_cooling_off_period() {
+    let mut h = Harness::with_config(with_cooling_off(60));
+
+    assert!(h.context.apply_config(with_day_start(with_cooling_off(60), 4), true));
+    assert_eq!(h.context.day_start_hour(), 0);
+    let pending = h.context.get_pending_changes();
+    assert!(pending.day_start_pending);
+    assert_eq!(pending.day_start_hour, 4);
+    assert_eq!(pending.day_start_at, h.context.now() + Duration::minutes(60));
+
+    // Neither a restart nor a repeated request skips the wait.
+    h.restart_with_config(with_day_start(with_cooling_off(60), 4));
+    assert_eq!(h.context.day_start_hour(), 0);
+    assert!(h.context.get_pending_changes().day_start_pending);
+
+    // Restoring the day start withdraws the request.
+    assert!(h.context.apply_config(with_cooling_off(60), true));
+    assert!(!h.context.get_pending_changes().day_start_pending);
+}
+
+#[test]
+fn a_locked_daemon_keeps_its_day_start_without_an_edit_session() {
+    let mut h = Harness::new();
+    h.persistence().set_password_hash(&user_daemon::auth::hash_password("secret"));
+    h.persistence().set_lock_state(true);
+
+    assert!(!h.context.apply_config(with_day_start(DaemonConfig::default(), 4), false));
+    assert_eq!(h.context.day_start_hour(), 0);
+    assert!(!h.context.get_pending_changes().day_start_pending);
+
+    h.context.apply_config(with_day_start(DaemonConfig::default(), 4), true);
+    assert_eq!(h.context.day_start_hour(), 4);
+}